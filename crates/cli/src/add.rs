@@ -0,0 +1,316 @@
+use {
+    crate::{complete::complete_crate, mirror::Mirror},
+    anyhow::{anyhow, Result},
+    clap::Args,
+    clap_complete::ArgValueCompleter,
+    panamax_search_lib::Index,
+    semver::VersionReq,
+    std::{
+        env::current_dir,
+        fs::{read_to_string, write},
+        path::PathBuf,
+    },
+    toml_edit::{Array, DocumentMut, InlineTable, Item, Table, Value},
+};
+
+#[derive(Args)]
+pub struct Add {
+    /// Crate to add, optionally with a version requirement like `serde@1.0`
+    #[arg(value_name = "CRATE[@REQ]", add = ArgValueCompleter::new(complete_crate))]
+    krate: String,
+
+    /// Features to enable (comma or space separated)
+    #[arg(short = 'F', long, value_name = "FEATURES")]
+    features: Vec<String>,
+
+    /// Disable the default features
+    #[arg(long)]
+    no_default_features: bool,
+
+    /// Mark the dependency as optional
+    #[arg(long)]
+    optional: bool,
+
+    /// Add as a development dependency
+    #[arg(long, conflicts_with = "build")]
+    dev: bool,
+
+    /// Add as a build dependency
+    #[arg(long)]
+    build: bool,
+
+    /// Path to Cargo.toml (default is the nearest Cargo.toml in the current or a parent directory)
+    #[arg(long, value_name = "PATH")]
+    manifest_path: Option<PathBuf>,
+}
+
+impl Add {
+    /**
+    Resolve the crate's version from the index and add it to Cargo.toml
+
+    Fully offline: the version and features come from the mirror instead of the registry. Crates
+    from a mirror other than the default one get a `registry` key with the mirror's name, which
+    must match a registry in cargo's configuration.
+    */
+    pub fn run(&self, index: &Index, mirrors: &[Mirror]) -> Result<()> {
+        let (name, req) = match self.krate.split_once('@') {
+            Some((name, req)) => (name, Some(req)),
+            None => (self.krate.as_str(), None),
+        };
+
+        let crate_ = index
+            .get(name)
+            .ok_or_else(|| anyhow!("Crate {name:?} not found in mirror"))?;
+
+        let features = self
+            .features
+            .iter()
+            .flat_map(|x| x.split([',', ' ']))
+            .filter(|x| !x.is_empty())
+            .map(String::from)
+            .collect::<Vec<_>>();
+
+        // Resolve the version, only reading the index file if there's a requirement
+        let mut details = None;
+        let version = match req {
+            Some(req) => {
+                let parsed = VersionReq::parse(req)
+                    .map_err(|e| anyhow!("Invalid version requirement {req:?}: {e}"))?;
+                details
+                    .insert(crate_.details()?)
+                    .versions
+                    .iter()
+                    .filter(|(version, yanked)| !yanked && parsed.matches(version))
+                    .map(|(version, _yanked)| version)
+                    .max()
+                    .cloned()
+                    .ok_or_else(|| {
+                        anyhow!("No non-yanked version of {name} matches {req:?} in mirror")
+                    })?
+            }
+            None => crate_
+                .latest_ny
+                .clone()
+                .ok_or_else(|| anyhow!("All versions of {name} are yanked"))?,
+        };
+
+        // Validate the features against the resolved version's features and dependencies
+        if !features.is_empty() {
+            let details = match details {
+                Some(details) if details.version == version => details,
+                _ => crate_.version_details(&version)?,
+            };
+            let unknown = features
+                .iter()
+                .filter(|x| match x.split_once('/') {
+                    // `dep/feat` or `dep?/feat` enables a feature of a dependency
+                    Some((dep, _feature)) => !details
+                        .dependencies
+                        .iter()
+                        .any(|x| x.name == dep.trim_end_matches('?')),
+                    None => !details.features.contains_key(*x),
+                })
+                .cloned()
+                .collect::<Vec<_>>();
+            if !unknown.is_empty() {
+                return Err(anyhow!(
+                    "{name} {version} does not have feature(s): {}; available features: {}",
+                    unknown.join(", "),
+                    details
+                        .features
+                        .keys()
+                        .cloned()
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                ));
+            }
+        }
+
+        // Crates from a mirror other than the default one need cargo's `registry` key
+        let mirror = crate_.registry().unwrap_or(&mirrors[0].name);
+        let registry = mirrors
+            .iter()
+            .find(|x| x.name == mirror)
+            .filter(|x| !x.default)
+            .map(|x| x.name.as_str());
+
+        // Like `cargo add`, use the requirement as given or else the full version
+        let version_req = req.map(String::from).unwrap_or_else(|| version.to_string());
+
+        let manifest_path = match &self.manifest_path {
+            Some(path) => path.clone(),
+            None => find_manifest()?,
+        };
+        let mut doc = read_to_string(&manifest_path)?
+            .parse::<DocumentMut>()
+            .map_err(|e| anyhow!("{manifest_path:?}: {e}"))?;
+        if !doc.contains_key("package") {
+            return Err(anyhow!(
+                "{manifest_path:?}: Virtual manifest; use --manifest-path to choose a package"
+            ));
+        }
+
+        let table_name = if self.dev {
+            "dev-dependencies"
+        } else if self.build {
+            "build-dependencies"
+        } else {
+            "dependencies"
+        };
+        let table = doc
+            .entry(table_name)
+            .or_insert_with(|| Item::Table(Table::new()))
+            .as_table_like_mut()
+            .ok_or_else(|| anyhow!("{manifest_path:?}: {table_name} is not a table"))?;
+        let sorted = table.iter().map(|(key, _value)| key).is_sorted();
+
+        self.update(table, name, &version_req, &features, registry);
+
+        if sorted {
+            if let Some(table) = doc[table_name].as_table_mut() {
+                table.sort_values();
+            } else if let Some(table) = doc[table_name].as_inline_table_mut() {
+                table.sort_values();
+            }
+        }
+
+        write(&manifest_path, doc.to_string())?;
+
+        eprintln!("      Adding {name} v{version} to {table_name}");
+        if !features.is_empty() {
+            eprintln!("             Features: {}", features.join(", "));
+        }
+
+        Ok(())
+    }
+
+    /// Insert or update the dependency, keeping any existing keys like `path` or `registry`
+    fn update(
+        &self,
+        table: &mut dyn toml_edit::TableLike,
+        name: &str,
+        version_req: &str,
+        features: &[String],
+        registry: Option<&str>,
+    ) {
+        let simple = features.is_empty()
+            && !self.no_default_features
+            && !self.optional
+            && registry.is_none();
+
+        let mut dependency = match table.get(name) {
+            Some(Item::Value(Value::InlineTable(x))) => x.clone(),
+            Some(Item::Table(x)) => x.clone().into_inline_table(),
+            _ if simple => {
+                table.insert(name, Item::Value(Value::from(version_req)));
+                return;
+            }
+            _ => InlineTable::new(),
+        };
+
+        dependency.insert("version", Value::from(version_req));
+
+        if !features.is_empty() {
+            let mut array = dependency
+                .get("features")
+                .and_then(|x| x.as_array())
+                .cloned()
+                .unwrap_or_else(Array::new);
+            for feature in features {
+                if !array.iter().any(|x| x.as_str() == Some(feature)) {
+                    array.push(feature.as_str());
+                }
+            }
+            dependency.insert("features", Value::Array(array));
+        }
+        if self.no_default_features {
+            dependency.insert("default-features", Value::from(false));
+        }
+        if self.optional {
+            dependency.insert("optional", Value::from(true));
+        }
+        if let Some(registry) = registry {
+            dependency.insert("registry", Value::from(registry));
+        }
+
+        match table.get_mut(name) {
+            Some(Item::Table(x)) => {
+                // Keep a `[dependencies.name]` table as a table
+                for (key, value) in dependency.iter() {
+                    x.insert(key, Item::Value(value.clone()));
+                }
+            }
+            _ => {
+                table.insert(name, Item::Value(Value::InlineTable(dependency)));
+            }
+        }
+    }
+}
+
+/// Find the nearest Cargo.toml in the current or a parent directory
+fn find_manifest() -> Result<PathBuf> {
+    let cwd = current_dir()?;
+    cwd.ancestors()
+        .map(|x| x.join("Cargo.toml"))
+        .find(|x| x.is_file())
+        .ok_or_else(|| anyhow!("Could not find Cargo.toml in {cwd:?} or any parent directory"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn add(no_default_features: bool) -> Add {
+        Add {
+            krate: String::from("serde"),
+            features: vec![],
+            no_default_features,
+            optional: false,
+            dev: false,
+            build: false,
+            manifest_path: None,
+        }
+    }
+
+    /// Update `[dependencies]` in a manifest and return the new manifest
+    fn update(add: &Add, manifest: &str, features: &[&str], registry: Option<&str>) -> String {
+        let mut doc = manifest.parse::<DocumentMut>().unwrap();
+        let table = doc["dependencies"].as_table_like_mut().unwrap();
+        let features = features.iter().map(|x| x.to_string()).collect::<Vec<_>>();
+        add.update(table, "serde", "1.0.200", &features, registry);
+        doc.to_string()
+    }
+
+    #[test]
+    fn new_dependency() {
+        let manifest = "[dependencies]\nanyhow = \"1\"\n";
+        assert_eq!(
+            update(&add(false), manifest, &[], None),
+            "[dependencies]\nanyhow = \"1\"\nserde = \"1.0.200\"\n"
+        );
+        assert_eq!(
+            update(&add(true), manifest, &["derive"], Some("internal")),
+            "[dependencies]\nanyhow = \"1\"\nserde = { version = \"1.0.200\", features = \
+             [\"derive\"], default-features = false, registry = \"internal\" }\n"
+        );
+    }
+
+    #[test]
+    fn existing_inline_table() {
+        let manifest = "[dependencies]\nserde = { version = \"1\", features = [\"rc\"] }\n";
+        assert_eq!(
+            update(&add(false), manifest, &["derive", "rc"], None),
+            "[dependencies]\nserde = { version = \"1.0.200\", features = [\"rc\", \"derive\"] }\n"
+        );
+    }
+
+    #[test]
+    fn existing_table() {
+        let manifest = "[dependencies.serde]\nversion = \"1\"\npath = \"../serde\"\n";
+        assert_eq!(
+            update(&add(false), manifest, &["derive"], None),
+            "[dependencies.serde]\nversion = \"1.0.200\"\npath = \"../serde\"\nfeatures = \
+             [\"derive\"]\n"
+        );
+    }
+}
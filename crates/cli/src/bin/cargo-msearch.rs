@@ -0,0 +1,114 @@
+use {
+    anyhow::Result,
+    clap::{ArgAction::Count, Args, ColorChoice, Parser},
+    expanduser::expanduser,
+    log::{info, LevelFilter::*},
+    panamax_search_lib::{auto_color, highlight_style, Ansi, CargoConfig, Highlighter, Index},
+    std::env::{current_dir, var},
+};
+
+#[derive(Parser)]
+#[command(name = "cargo", bin_name = "cargo")]
+enum Cargo {
+    Msearch(Msearch),
+}
+
+/// Search crates in a panamax mirror like `cargo search`
+#[derive(Args)]
+#[command(version, about, max_term_width = 80)]
+struct Msearch {
+    /// Limit the number of results
+    #[arg(long, value_name = "LIMIT", default_value_t = 10)]
+    limit: usize,
+
+    /// Local registry index URL to search, like `file:///srv/panamax/crates.io-index`
+    #[arg(long, value_name = "INDEX", conflicts_with = "registry")]
+    index: Option<String>,
+
+    /// Registry to search (default is `registry.default` or crates.io, following source
+    /// replacement in `.cargo/config.toml` to a local mirror)
+    #[arg(long, value_name = "REGISTRY")]
+    registry: Option<String>,
+
+    /// Mirror directory (default is the local mirror from `.cargo/config.toml`, else `~/panamax`)
+    #[arg(short, long, value_name = "PATH", conflicts_with_all = ["index", "registry"])]
+    mirror: Option<String>,
+
+    /// Use verbose output
+    #[arg(short, action = Count)]
+    verbose: u8,
+
+    /// Do not print warnings or the number of omitted results
+    #[arg(short, long, conflicts_with = "verbose")]
+    quiet: bool,
+
+    /// Coloring
+    #[arg(long, value_name = "WHEN", default_value_t = ColorChoice::Auto)]
+    color: ColorChoice,
+
+    /// Search queries
+    #[arg(value_name = "QUERY", required = true)]
+    query: Vec<String>,
+}
+
+fn main() -> Result<()> {
+    let Cargo::Msearch(cli) = Cargo::parse();
+
+    env_logger::builder()
+        .filter_level(if cli.quiet {
+            Off
+        } else {
+            match cli.verbose {
+                0 => Warn,  // Default level
+                1 => Info,  // -v
+                2 => Debug, // -vv
+                _ => Trace, // -vvv
+            }
+        })
+        .init();
+
+    let mirror = if let Some(mirror) = &cli.mirror {
+        expanduser(mirror)?
+    } else if let Some(index) = &cli.index {
+        CargoConfig::index_url_directory(index)?
+    } else {
+        let cargo_home =
+            expanduser(var("CARGO_HOME").unwrap_or_else(|_| String::from("~/.cargo")))?;
+        let config = CargoConfig::load(&current_dir()?, &cargo_home)?;
+        match config.mirror_directory(cli.registry.as_deref()) {
+            Ok(mirror) => mirror,
+            Err(e) if cli.registry.is_some() => return Err(e),
+            Err(e) => {
+                info!("{e}; using ~/panamax");
+                expanduser("~/panamax")?
+            }
+        }
+    };
+
+    let ansi = Ansi(highlight_style());
+    let highlighter = match cli.color {
+        ColorChoice::Auto => auto_color(),
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+    }
+    .then_some(&ansi as &dyn Highlighter);
+
+    let index = Index::load(&mirror)?;
+    let mut search = index
+        .search_builder(&cli.query)
+        .case_insensitive(true)
+        .build()?
+        .to_search();
+    let total = search.len();
+    search.truncate(cli.limit);
+
+    print!("{}", search.to_string(false, highlighter));
+    if total > cli.limit && !cli.quiet {
+        println!(
+            "... and {} crates more (use --limit N to see more)",
+            total - cli.limit
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,64 @@
+use {
+    crate::{config::Config, mirror::select_mirrors},
+    anyhow::Result,
+    clap::Command,
+    clap_complete::{env::Shells, CompletionCandidate, Shell},
+    panamax_search_lib::Index,
+    std::{collections::BTreeMap, ffi::OsStr, io::stdout},
+};
+
+/**
+Write the shell's completion script
+
+The script calls back into `panamax-search` via the `COMPLETE` environment variable so that crate
+names can be completed from the cache files.
+*/
+pub fn completions(cmd: &Command, shell: Shell) -> Result<()> {
+    let name = cmd.get_name();
+    Shells::builtins()
+        .completer(&shell.to_string())
+        .unwrap()
+        .write_registration("COMPLETE", name, name, name, &mut stdout())?;
+    Ok(())
+}
+
+/// Write the man page
+pub fn man(cmd: Command) -> Result<()> {
+    clap_mangen::Man::new(cmd).render(&mut stdout())?;
+    Ok(())
+}
+
+/**
+Complete crate names from the cache files of the config file's mirrors (or `~/panamax`)
+
+Reads the cache files as is without checking whether they're up to date since rebuilding would be
+far too slow for completion.
+*/
+pub fn complete_crate(current: &OsStr) -> Vec<CompletionCandidate> {
+    let Some(current) = current.to_str() else {
+        return vec![];
+    };
+    let Ok(mirrors) =
+        Config::load().and_then(|config| select_mirrors(&[], None, None, None, &config))
+    else {
+        return vec![];
+    };
+
+    let mut r = BTreeMap::new();
+    for mirror in mirrors {
+        let Ok(index) = Index::read_cache_file(&mirror.cache.join("search.json")) else {
+            continue;
+        };
+        for crate_ in index.prefix(current) {
+            r.entry(crate_.name.clone())
+                .or_insert_with(|| crate_.description.clone());
+        }
+    }
+
+    r.into_iter()
+        .map(|(name, description)| {
+            CompletionCandidate::new(name)
+                .help(description.map(|x| x.lines().next().unwrap_or_default().to_string().into()))
+        })
+        .collect()
+}
@@ -0,0 +1,214 @@
+use {
+    anyhow::{anyhow, Result},
+    clap::ValueEnum,
+    expanduser::expanduser,
+    log::info,
+    serde::Deserialize,
+    std::{
+        env::{current_dir, var},
+        fs::read_to_string,
+        path::{Path, PathBuf},
+    },
+};
+
+/// Name of the project-local config file, found in the current or a parent directory
+const LOCAL_CONFIG_FILE: &str = ".panamax-search.toml";
+
+#[derive(Clone, Copy, Debug, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    Text,
+    Json,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Color {
+    Auto,
+    Always,
+    Never,
+}
+
+/**
+Defaults from `~/.config/panamax-search/config.toml` (or `$XDG_CONFIG_HOME/panamax-search`),
+overridden by the nearest `.panamax-search.toml` in the current or a parent directory
+
+Command line options take precedence over both.
+
+```toml
+mirrors = ["/srv/panamax", "internal=/srv/registry"]
+cache = "~/.cache/panamax-search"
+case-sensitive = true
+include-yanked = false
+format = "text"
+color = "auto"
+limit = 20
+registry = "panamax"
+```
+
+Relative paths are relative to the directory containing the config file.
+*/
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Config {
+    /// Mirror directories like `-m`
+    pub mirrors: Option<Vec<String>>,
+
+    /// Directory for the cache files, with a subdirectory per mirror, instead of each mirror
+    /// directory
+    pub cache: Option<String>,
+
+    pub case_sensitive: Option<bool>,
+    pub include_yanked: Option<bool>,
+    pub format: Option<Format>,
+    pub color: Option<Color>,
+    pub limit: Option<usize>,
+
+    /// Name of the mirror to search by default
+    pub registry: Option<String>,
+}
+
+impl Config {
+    /// Load the user config file and the project-local override, if any
+    pub fn load() -> Result<Config> {
+        let mut r = Config::default();
+
+        let config_home = match var("XDG_CONFIG_HOME") {
+            Ok(x) if !x.is_empty() => PathBuf::from(x),
+            _ => expanduser("~/.config")?,
+        };
+        let user = config_home.join("panamax-search").join("config.toml");
+        if user.is_file() {
+            r = Config::read(&user)?;
+        }
+
+        let cwd = current_dir()?;
+        if let Some(local) = cwd
+            .ancestors()
+            .map(|x| x.join(LOCAL_CONFIG_FILE))
+            .find(|x| x.is_file())
+        {
+            r = r.merge(Config::read(&local)?);
+        }
+
+        Ok(r)
+    }
+
+    fn read(path: &Path) -> Result<Config> {
+        info!("Load config file {path:?}");
+        let mut r = toml::from_str::<Config>(&read_to_string(path)?)
+            .map_err(|e| anyhow!("{path:?}: {e}"))?;
+
+        // Resolve paths relative to the config file's directory
+        let directory = path.parent().unwrap();
+        if let Some(mirrors) = &mut r.mirrors {
+            for mirror in mirrors.iter_mut() {
+                *mirror = match mirror.split_once('=') {
+                    Some((name, path)) => format!("{name}={}", resolve(directory, path)?),
+                    None => resolve(directory, mirror)?,
+                };
+            }
+        }
+        if let Some(cache) = &mut r.cache {
+            *cache = resolve(directory, cache)?;
+        }
+
+        Ok(r)
+    }
+
+    /// Override this config's settings with any that are set in another config
+    fn merge(self, other: Config) -> Config {
+        Config {
+            mirrors: other.mirrors.or(self.mirrors),
+            cache: other.cache.or(self.cache),
+            case_sensitive: other.case_sensitive.or(self.case_sensitive),
+            include_yanked: other.include_yanked.or(self.include_yanked),
+            format: other.format.or(self.format),
+            color: other.color.or(self.color),
+            limit: other.limit.or(self.limit),
+            registry: other.registry.or(self.registry),
+        }
+    }
+}
+
+/// Boolean setting from a pair of command line flags like `-s`/`-i`, else the config file, else
+/// `false`
+pub fn flag(on: bool, off: bool, config: Option<bool>) -> bool {
+    if on || off {
+        on
+    } else {
+        config.unwrap_or(false)
+    }
+}
+
+fn resolve(directory: &Path, path: &str) -> Result<String> {
+    Ok(directory
+        .join(expanduser(path)?)
+        .to_string_lossy()
+        .to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, std::fs::write, tempfile::TempDir};
+
+    #[test]
+    fn merge_prefers_the_other_config() {
+        let user = Config {
+            mirrors: Some(vec![String::from("/srv/panamax")]),
+            case_sensitive: Some(true),
+            limit: Some(10),
+            registry: Some(String::from("panamax")),
+            ..Default::default()
+        };
+        let local = Config {
+            case_sensitive: Some(false),
+            format: Some(Format::Json),
+            registry: Some(String::from("internal")),
+            ..Default::default()
+        };
+
+        let r = user.merge(local);
+        assert_eq!(r.mirrors.unwrap(), ["/srv/panamax"]);
+        assert_eq!(r.case_sensitive, Some(false));
+        assert!(matches!(r.format, Some(Format::Json)));
+        assert_eq!(r.limit, Some(10));
+        assert_eq!(r.registry.as_deref(), Some("internal"));
+        assert!(r.cache.is_none());
+    }
+
+    #[test]
+    fn flags_take_precedence() {
+        assert!(flag(true, false, Some(false)));
+        assert!(!flag(false, true, Some(true)));
+        assert!(flag(false, false, Some(true)));
+        assert!(!flag(false, false, None));
+    }
+
+    #[test]
+    fn read_resolves_relative_paths() {
+        let dir = TempDir::new().unwrap();
+        let file = dir.path().join(LOCAL_CONFIG_FILE);
+        write(
+            &file,
+            "mirrors = [\"panamax\", \"internal=/srv/registry\"]\ncache = \"cache\"\n",
+        )
+        .unwrap();
+
+        let r = Config::read(&file).unwrap();
+        assert_eq!(
+            r.mirrors.unwrap(),
+            [
+                dir.path().join("panamax").to_string_lossy().to_string(),
+                String::from("internal=/srv/registry"),
+            ]
+        );
+        assert_eq!(
+            r.cache.unwrap(),
+            dir.path().join("cache").to_string_lossy().to_string()
+        );
+
+        write(&file, "mirror = \"/srv/panamax\"\n").unwrap();
+        assert!(Config::read(&file).is_err());
+    }
+}
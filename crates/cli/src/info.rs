@@ -0,0 +1,41 @@
+use {
+    crate::complete::complete_crate,
+    anyhow::{anyhow, Result},
+    clap::Args,
+    clap_complete::ArgValueCompleter,
+    panamax_search_lib::Index,
+};
+
+#[derive(Args)]
+pub struct Info {
+    /// Crate name
+    #[arg(value_name = "CRATE", add = ArgValueCompleter::new(complete_crate))]
+    krate: String,
+}
+
+impl Info {
+    /// Print the crate's versions, license, features, and dependencies from the mirror
+    pub fn run(&self, index: &Index, include_yanked: bool) -> Result<()> {
+        let crate_ = index
+            .get(&self.krate)
+            .ok_or_else(|| anyhow!("Crate {:?} not found in mirror", self.krate))?;
+
+        for (i, (crate_, _tag)) in crate_.tagged().into_iter().enumerate() {
+            if i > 0 {
+                println!();
+            }
+            println!("{} = \"{}\"", crate_.name, crate_.version(include_yanked));
+            if let Some(description) = &crate_.description {
+                println!("{description}");
+            }
+            println!();
+            if let Some(registry) = &crate_.registry {
+                println!("Registry: {registry}");
+            }
+
+            print!("{}", crate_.details()?);
+        }
+
+        Ok(())
+    }
+}
@@ -1,40 +1,215 @@
 use {
     anyhow::{anyhow, Result},
-    clap::{ArgAction::Count, Parser},
+    clap::{ArgAction::Count, CommandFactory, Parser, Subcommand, ValueEnum},
+    clap_complete::{CompleteEnv, Shell},
+    config::{flag, Color, Config, Format},
     expanduser::expanduser,
     log::LevelFilter::*,
-    panamax_search_lib::Index,
+    mirror::{load, print_reports, select_mirrors},
+    panamax_search_lib::{
+        auto_color, highlight_style, Ansi, DescriptionReport, Diff, Fields, Highlighter, Index,
+        SearchOptions, Sort, Stats,
+    },
+    progress::Report,
+    std::path::PathBuf,
 };
 
+mod add;
+mod complete;
+mod config;
+mod info;
+mod mirror;
+mod progress;
+mod tui;
+mod watch;
+
 #[derive(Parser)]
-#[command(about, version, max_term_width = 80)]
+#[command(
+    about,
+    version,
+    max_term_width = 80,
+    subcommand_precedence_over_arg = true
+)]
 struct Cli {
-    /// Force update the cache file and exit
-    #[arg(short = 'U', conflicts_with_all = ["search", "include_yanked", "case_sensitive"])]
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Force update the cache file and exit, showing progress on a terminal and a summary of each
+    /// phase's count and time
+    #[arg(
+        short = 'U',
+        conflicts_with_all = ["search", "include_yanked", "case_sensitive", "symbol", "interactive"],
+    )]
     update: bool,
 
-    /// Mirror directory
-    #[arg(short, value_name = "PATH", default_value = "~/panamax")]
-    mirror: String,
+    /// Also index public items of each crate (with -U; slow)
+    #[arg(long, requires = "update")]
+    index_symbols: bool,
+
+    /// Also write a static HTML page of new crates and versions next to the Atom feed (with -U)
+    ///
+    /// The feed is written next to the cache file.
+    #[arg(long, requires = "update")]
+    feed_html: bool,
+
+    /// Mirror directory; repeat to search multiple mirrors, optionally named like
+    /// `internal=/srv/registry` (default name is the directory name; default mirror is
+    /// `~/panamax`)
+    #[arg(short, value_name = "[NAME=]PATH", global = true)]
+    mirror: Vec<String>,
+
+    /// Only use the mirror with this name
+    #[arg(long, value_name = "NAME", global = true)]
+    registry: Option<String>,
+
+    /// Keep cache files in a subdirectory of this directory per mirror instead of in each mirror
+    /// directory
+    #[arg(long, value_name = "PATH", global = true)]
+    cache: Option<String>,
+
+    /// Search the crates cargo has downloaded instead of a mirror (default path is $CARGO_HOME
+    /// or ~/.cargo)
+    #[arg(
+        long,
+        value_name = "PATH",
+        num_args = 0..=1,
+        default_missing_value = "",
+        conflicts_with = "mirror",
+        global = true
+    )]
+    cargo_home: Option<String>,
 
     /// Verbose (default=warn; -v=info; -vv=debug; -vvv=trace)
-    #[arg(short, action = Count)]
+    #[arg(short, action = Count, global = true)]
     verbose: u8,
 
     /// Include yanked
     #[arg(short = 'y')]
     include_yanked: bool,
 
+    /// Exclude yanked (overrides the config file)
+    #[arg(long, conflicts_with = "include_yanked")]
+    exclude_yanked: bool,
+
     /// Case sensitive
     #[arg(short = 's')]
     case_sensitive: bool,
 
+    /// Case insensitive (overrides the config file)
+    #[arg(long, conflicts_with = "case_sensitive")]
+    ignore_case: bool,
+
+    /// Maximum number of results
+    #[arg(long, value_name = "N")]
+    limit: Option<usize>,
+
+    /// Match queries as literal strings instead of regular expressions
+    #[arg(short = 'F', long)]
+    fixed_strings: bool,
+
+    /// Only match queries against crate names or descriptions
+    #[arg(long, value_enum, value_name = "FIELD")]
+    field: Option<Field>,
+
+    /// Order of results
+    #[arg(long, value_enum, default_value_t = Order::Relevance)]
+    sort: Order,
+
+    /// Output format [default: text]
+    #[arg(long, value_enum)]
+    format: Option<Format>,
+
+    /// Highlight matches [default: auto]
+    #[arg(long, value_enum, global = true)]
+    color: Option<Color>,
+
+    /// Interactive mode: filter as you type, view details and README, copy `name = "version"`
+    #[arg(short, conflicts_with = "symbol")]
+    interactive: bool,
+
+    /// Search public item names instead of crates (requires a prior -U --index-symbols)
+    #[arg(long)]
+    symbol: bool,
+
     /// Search queries
     #[arg(value_name = "QUERY")]
     search: Vec<String>,
 }
 
+#[derive(Subcommand)]
+enum Command {
+    /// Add a dependency to Cargo.toml using the version and features in the mirror
+    Add(add::Add),
+
+    /// Show a crate's versions, license, features, and dependencies
+    Info(info::Info),
+
+    /// Report new crates, new versions, newly yanked versions, removed crates, and changed
+    /// descriptions since the previous cache file
+    Diff {
+        /// Previous cache file (default is the `search.prev.json` kept by the last rebuild)
+        #[arg(value_name = "PATH")]
+        previous: Option<PathBuf>,
+
+        /// Output JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Report crate and version counts, yanked-only crates, crates without a description, license,
+    /// edition, and Rust version distributions, top categories and keywords, and crate file sizes
+    ///
+    /// Computed from the cache file; a cache file saved by an older version only has the counts
+    /// until the next -U.
+    Stats {
+        /// Number of licenses, categories, and keywords to show
+        #[arg(long, value_name = "N", default_value_t = 10)]
+        top: usize,
+
+        /// Output JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// List the crates whose descriptions are missing, by reason (no description, corrupt crate
+    /// file, malformed Cargo.toml, etc.), and those whose quirks were worked around
+    Doctor {
+        /// Output JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Watch the mirror and update the cache file and feed after each sync
+    Watch(watch::Watch),
+
+    /// Print the completion script for a shell, which also completes crate names
+    ///
+    /// For example, add `source <(panamax-search completions bash)` to `~/.bashrc`.
+    Completions {
+        #[arg(value_name = "SHELL")]
+        shell: Shell,
+    },
+
+    /// Print the man page
+    Man,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Field {
+    Name,
+    Description,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Order {
+    Relevance,
+    Name,
+}
+
 fn main() -> Result<()> {
+    // Respond to completion requests from the shell
+    CompleteEnv::with_factory(Cli::command).complete();
+
     let cli = Cli::parse();
 
     env_logger::builder()
@@ -46,22 +221,146 @@ fn main() -> Result<()> {
         })
         .init();
 
-    let mirror = expanduser(&cli.mirror).unwrap();
+    match &cli.command {
+        Some(Command::Completions { shell }) => {
+            return complete::completions(&Cli::command(), *shell)
+        }
+        Some(Command::Man) => return complete::man(Cli::command()),
+        _ => {}
+    }
 
-    if cli.update {
-        // Force update and exit
-        Index::load_from_mirror_directory(&mirror)?;
+    let config = Config::load()?;
+    let case_sensitive = flag(cli.case_sensitive, cli.ignore_case, config.case_sensitive);
+    let include_yanked = flag(
+        cli.include_yanked,
+        cli.exclude_yanked,
+        config.include_yanked,
+    );
+    let format = cli.format.or(config.format).unwrap_or(Format::Text);
+    let ansi = Ansi(highlight_style());
+    let highlighter = match cli.color.or(config.color).unwrap_or(Color::Auto) {
+        Color::Auto => auto_color(),
+        Color::Always => true,
+        Color::Never => false,
+    }
+    .then_some(&ansi as &dyn Highlighter);
+    let limit = cli.limit.or(config.limit);
+
+    let mirrors = select_mirrors(
+        &cli.mirror,
+        cli.cargo_home.as_deref(),
+        cli.registry.as_deref(),
+        cli.cache.as_deref(),
+        &config,
+    )?;
+
+    if let Some(command) = &cli.command {
+        match command {
+            Command::Add(add) => add.run(&load(&mirrors)?, &mirrors),
+            Command::Info(info) => info.run(&load(&mirrors)?, include_yanked),
+            Command::Diff { previous, json } => {
+                if previous.is_some() && mirrors.len() > 1 {
+                    return Err(anyhow!(
+                        "Cannot diff multiple mirrors against one cache file"
+                    ));
+                }
+                print_reports(
+                    &mirrors,
+                    *json || matches!(format, Format::Json),
+                    |mirror| {
+                        Ok(mirror.load()?.diff(&match previous {
+                            Some(path) => {
+                                Index::read_cache_file(&expanduser(path.to_string_lossy())?)?
+                            }
+                            None => Index::load_snapshot(&mirror.cache)?,
+                        }))
+                    },
+                    Diff::to_json,
+                )
+            }
+            Command::Stats { top, json } => print_reports(
+                &mirrors,
+                *json || matches!(format, Format::Json),
+                |mirror| Ok(mirror.load()?.stats(*top)),
+                Stats::to_json,
+            ),
+            Command::Doctor { json } => print_reports(
+                &mirrors,
+                *json || matches!(format, Format::Json),
+                |mirror| Ok(mirror.load()?.description_report()),
+                DescriptionReport::to_json,
+            ),
+            Command::Watch(watch) => watch.run(&mirrors),
+            Command::Completions { .. } | Command::Man => unreachable!(),
+        }
+    } else if cli.update {
+        // Force update, add the new crates and versions to the feed, and exit
+        for mirror in &mirrors {
+            let report = Report::new(cli.verbose > 0);
+            let index = Index::rebuild_with_progress(
+                &mirror.path,
+                &mirror.cache,
+                cli.index_symbols,
+                &|x| report.update(x),
+            )?;
+            mirror.update_feed(&index, cli.feed_html)?;
+            eprint!("{}", report.summary(&mirror.name));
+        }
+        Ok(())
+    } else if cli.interactive {
+        let index = load(&mirrors)?;
+        if let Some(line) = tui::run(
+            &index,
+            cli.search.join(" "),
+            !case_sensitive,
+            include_yanked,
+        )? {
+            println!("{line}");
+        }
         Ok(())
     } else if cli.search.is_empty() {
         Err(anyhow!("No search query"))
+    } else if cli.symbol {
+        let index = load(&mirrors)?;
+        if !index.has_symbols() {
+            return Err(anyhow!(
+                "No public items in cache file; run `panamax-search -U --index-symbols`"
+            ));
+        }
+        let mut search = index.search_symbols(&cli.search, !case_sensitive)?;
+        if let Some(limit) = limit {
+            search.truncate(limit);
+        }
+        match format {
+            Format::Text => println!("{}", search.to_string(include_yanked, highlighter)),
+            Format::Json => println!("{}", search.to_json(include_yanked)?),
+        }
+        Ok(())
     } else {
-        let index = Index::load(&mirror)?;
-        println!(
-            "{}",
-            index
-                .search(&cli.search, !cli.case_sensitive)
-                .to_string(cli.include_yanked, true),
-        );
+        let index = load(&mirrors)?;
+        let results = index.search_with(
+            &cli.search,
+            SearchOptions {
+                case_insensitive: !case_sensitive,
+                literal: cli.fixed_strings,
+                fields: match cli.field {
+                    Some(Field::Name) => Fields::Name,
+                    Some(Field::Description) => Fields::Description,
+                    None => Fields::All,
+                },
+                include_yanked,
+                limit,
+                sort: match cli.sort {
+                    Order::Relevance => Sort::Relevance,
+                    Order::Name => Sort::Name,
+                },
+                ..Default::default()
+            },
+        )?;
+        match format {
+            Format::Text => println!("{}", results.to_string(highlighter)),
+            Format::Json => println!("{}", results.to_json()?),
+        }
         Ok(())
     }
 }
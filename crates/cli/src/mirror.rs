@@ -0,0 +1,248 @@
+use {
+    crate::config::Config,
+    anyhow::{anyhow, Result},
+    expanduser::expanduser,
+    panamax_search_lib::{Diff, Feed, Index},
+    std::{env::var, fmt::Display, fs::copy, path::PathBuf, time::SystemTime},
+};
+
+/// Mirror directory with its registry name and the directory containing its cache file
+pub struct Mirror {
+    pub name: String,
+    pub path: PathBuf,
+    pub cache: PathBuf,
+
+    /// Whether this is the default mirror, which replaces crates.io: the first mirror, or cargo's
+    /// crates.io registry with `--cargo-home`
+    pub default: bool,
+}
+
+impl Mirror {
+    pub fn load(&self) -> Result<Index> {
+        Ok(Index::load_with_cache_directory(&self.path, &self.cache)?)
+    }
+
+    /**
+    Add the new crates and versions since the previous feed update to the feed in the cache
+    directory
+
+    The cache file is copied to `feed.search.json` after each update to diff against next time, so
+    crates found by a rebuild in between, e.g. when a search updates a stale cache file, aren't
+    missed.
+    */
+    pub fn update_feed(&self, index: &Index, html: bool) -> Result<Diff> {
+        let snapshot_file = self.cache.join("feed.search.json");
+
+        // The first build has no snapshot and nothing is new yet
+        let diff = match Index::read_cache_file(&snapshot_file)
+            .or_else(|_| Index::load_snapshot(&self.cache))
+        {
+            Ok(previous) => index.diff(&previous),
+            Err(_) => Diff::default(),
+        };
+        let mut feed = Feed::load(&self.cache)?;
+        feed.update(&diff, SystemTime::now());
+        feed.save(&self.cache, &self.name, html)?;
+        copy(self.cache.join("search.json"), snapshot_file)?;
+        Ok(diff)
+    }
+}
+
+/**
+Select the mirrors from the command line (`-m`, `--cargo-home`, `--registry`, `--cache`), else the
+config file, else `~/panamax`
+*/
+pub fn select_mirrors(
+    mirror: &[String],
+    cargo_home: Option<&str>,
+    registry: Option<&str>,
+    cache: Option<&str>,
+    config: &Config,
+) -> Result<Vec<Mirror>> {
+    let mut registries = if let Some(cargo_home) = cargo_home {
+        let cargo_home = if cargo_home.is_empty() {
+            var("CARGO_HOME").unwrap_or_else(|_| String::from("~/.cargo"))
+        } else {
+            cargo_home.to_string()
+        };
+        Index::cargo_registries(&expanduser(cargo_home)?)?
+    } else if !mirror.is_empty() {
+        mirrors(mirror)?
+    } else {
+        mirrors(
+            config
+                .mirrors
+                .as_deref()
+                .unwrap_or(&[String::from("~/panamax")]),
+        )?
+    };
+
+    let default = if cargo_home.is_some() {
+        registries
+            .iter()
+            .find(|(name, _path)| name.starts_with("index.crates.io"))
+    } else {
+        registries.first()
+    }
+    .map(|(name, _path)| name.clone());
+
+    // The config file's registry only applies to the config file's mirrors
+    let registry = registry.or(if mirror.is_empty() && cargo_home.is_none() {
+        config.registry.as_deref()
+    } else {
+        None
+    });
+    if let Some(registry) = registry {
+        if !registries.iter().any(|(name, _path)| name == registry) {
+            return Err(anyhow!(
+                "No mirror named {registry:?}; available: {}",
+                registries
+                    .iter()
+                    .map(|(name, _path)| name.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            ));
+        }
+        registries.retain(|(name, _path)| name == registry);
+    }
+
+    let cache = cache
+        .or(config.cache.as_deref())
+        .map(expanduser)
+        .transpose()?;
+    Ok(registries
+        .into_iter()
+        .map(|(name, path)| Mirror {
+            cache: cache
+                .as_ref()
+                .map(|x| x.join(&name))
+                .unwrap_or_else(|| path.clone()),
+            default: default.as_ref() == Some(&name),
+            name,
+            path,
+        })
+        .collect())
+}
+
+/// Parse `[NAME=]PATH` mirror arguments into unique registry names and expanded paths
+fn mirrors(args: &[String]) -> Result<Vec<(String, PathBuf)>> {
+    let mut r: Vec<(String, PathBuf)> = vec![];
+
+    for arg in args {
+        let (name, path) = match arg.split_once('=') {
+            Some((name, path)) => (name.to_string(), expanduser(path)?),
+            None => {
+                let path = expanduser(arg)?;
+                let name = path
+                    .file_name()
+                    .map(|x| x.to_string_lossy().to_string())
+                    .unwrap_or_else(|| arg.clone());
+                (name, path)
+            }
+        };
+
+        if r.iter().any(|(x, _path)| *x == name) {
+            return Err(anyhow!(
+                "Duplicate mirror name {name:?}; use `-m NAME=PATH` to name each mirror"
+            ));
+        }
+
+        r.push((name, path));
+    }
+
+    Ok(r)
+}
+
+/// Load a single mirror's index as is, or merge multiple mirrors' indexes with registry tags
+pub fn load(mirrors: &[Mirror]) -> Result<Index> {
+    if mirrors.len() == 1 {
+        mirrors[0].load()
+    } else {
+        Ok(Index::merge(
+            mirrors
+                .iter()
+                .map(|x| Ok((x.name.clone(), x.load()?)))
+                .collect::<Result<Vec<_>>>()?,
+        ))
+    }
+}
+
+/// Print a report of each mirror as text or JSON, under a `# name` header if there are several
+pub fn print_reports<T: Display>(
+    mirrors: &[Mirror],
+    json: bool,
+    report: impl Fn(&Mirror) -> Result<T>,
+    to_json: impl Fn(&T) -> Result<String>,
+) -> Result<()> {
+    for mirror in mirrors {
+        let report = report(mirror)?;
+        if mirrors.len() > 1 {
+            println!("# {}\n", mirror.name);
+        }
+        if json {
+            println!("{}", to_json(&report)?);
+        } else {
+            print!("{report}");
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(mirrors: &[Mirror]) -> Vec<&str> {
+        mirrors.iter().map(|x| x.name.as_str()).collect()
+    }
+
+    #[test]
+    fn command_line_mirrors_take_precedence() {
+        let config = Config {
+            mirrors: Some(vec![
+                String::from("/srv/panamax"),
+                String::from("internal=/srv/registry"),
+            ]),
+            cache: Some(String::from("/var/cache/panamax-search")),
+            registry: Some(String::from("internal")),
+            ..Default::default()
+        };
+
+        // The config file's mirrors, registry, and cache
+        let r = select_mirrors(&[], None, None, None, &config).unwrap();
+        assert_eq!(names(&r), ["internal"]);
+        assert_eq!(
+            r[0].cache,
+            PathBuf::from("/var/cache/panamax-search/internal")
+        );
+        assert!(!r[0].default);
+
+        // `--registry` overrides the config file's registry
+        let r = select_mirrors(&[], None, Some("panamax"), None, &config).unwrap();
+        assert_eq!(names(&r), ["panamax"]);
+        assert!(r[0].default);
+
+        // `-m` replaces the config file's mirrors and ignores its registry
+        let r = select_mirrors(
+            &[String::from("/srv/other"), String::from("x=/srv/x")],
+            None,
+            None,
+            Some("/tmp/cache"),
+            &config,
+        )
+        .unwrap();
+        assert_eq!(names(&r), ["other", "x"]);
+        assert_eq!(r[1].cache, PathBuf::from("/tmp/cache/x"));
+        assert!(r[0].default && !r[1].default);
+
+        assert!(select_mirrors(&[], None, Some("missing"), None, &config).is_err());
+        assert!(select_mirrors(
+            &[String::from("/a/panamax"), String::from("/b/panamax")],
+            None,
+            None,
+            None,
+            &config,
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,128 @@
+use {
+    indicatif::{ProgressBar, ProgressStyle},
+    panamax_search_lib::{Phase, Progress},
+    std::{
+        fmt::Write,
+        io::{stderr, IsTerminal},
+        sync::Mutex,
+        time::{Duration, Instant},
+    },
+};
+
+/// Count and timing of a rebuild phase
+struct Timing {
+    phase: Phase,
+    done: usize,
+    start: Instant,
+    elapsed: Option<Duration>,
+}
+
+/**
+Progress of a rebuild: a progress bar per phase on a terminal, and a summary of each phase's count
+and timing afterwards
+*/
+pub struct Report {
+    bar: Option<ProgressBar>,
+    start: Instant,
+    timings: Mutex<Vec<Timing>>,
+}
+
+impl Report {
+    /// Only draw progress bars on a terminal, and not between log messages
+    pub fn new(verbose: bool) -> Report {
+        Report {
+            bar: (stderr().is_terminal() && !verbose).then(ProgressBar::no_length),
+            start: Instant::now(),
+            timings: Mutex::new(vec![]),
+        }
+    }
+
+    /// Progress callback for [`Index::rebuild_with_progress`](panamax_search_lib::Index)
+    pub fn update(&self, progress: Progress) {
+        let mut timings = self.timings.lock().unwrap();
+
+        if timings.last().is_none_or(|x| x.phase != progress.phase) {
+            timings.push(Timing {
+                phase: progress.phase,
+                done: 0,
+                start: Instant::now(),
+                elapsed: None,
+            });
+            if let Some(bar) = &self.bar {
+                bar.set_length(progress.total.unwrap_or(0) as u64);
+                bar.set_position(0);
+                bar.set_style(style(progress.total.is_some()));
+                bar.set_message(progress.phase.to_string());
+            }
+        }
+
+        let timing = timings.last_mut().unwrap();
+        timing.done = timing.done.max(progress.done);
+        if progress.is_finished() {
+            timing.elapsed = Some(timing.start.elapsed());
+        }
+        if let Some(bar) = &self.bar {
+            bar.set_position(timing.done as u64);
+            if progress.phase == Phase::Save && progress.is_finished() {
+                bar.finish_and_clear();
+            }
+        }
+    }
+
+    /**
+    Count and time of each phase
+
+    ```text
+    panamax: Rebuilt in 2m 15.3s
+      Index files  152340  0.8s
+      Parse        152340  10.5s
+      Crate files  152301  2m 3.0s
+      Save         1       1.1s
+    ```
+    */
+    pub fn summary(&self, name: &str) -> String {
+        if let Some(bar) = &self.bar {
+            bar.finish_and_clear();
+        }
+        let timings = self.timings.lock().unwrap();
+        let width = timings
+            .iter()
+            .map(|x| x.done.to_string().len())
+            .max()
+            .unwrap_or(0);
+
+        let mut r = format!("{name}: Rebuilt in {}\n", duration(self.start.elapsed()));
+        for timing in timings.iter() {
+            writeln!(
+                r,
+                "  {:<11}  {:<width$}  {}",
+                timing.phase.to_string(),
+                timing.done,
+                timing.elapsed.map(duration).as_deref().unwrap_or("-"),
+            )
+            .unwrap();
+        }
+        r
+    }
+}
+
+/// Bar with a known length, else a spinner
+fn style(length: bool) -> ProgressStyle {
+    ProgressStyle::with_template(if length {
+        "{msg:>11} [{bar:40}] {pos}/{len} ({eta})"
+    } else {
+        "{msg:>11} {spinner} {pos}"
+    })
+    .unwrap()
+    .progress_chars("=> ")
+}
+
+/// Duration like `1.1s` or `2m 3.0s`
+fn duration(d: Duration) -> String {
+    let seconds = d.as_secs_f64();
+    if seconds < 60.0 {
+        format!("{seconds:.1}s")
+    } else {
+        format!("{}m {:.1}s", d.as_secs() / 60, seconds % 60.0)
+    }
+}
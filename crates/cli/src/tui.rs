@@ -0,0 +1,395 @@
+use {
+    anyhow::Result,
+    panamax_search_lib::{Details, Index},
+    ratatui::{
+        crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers},
+        layout::{Constraint, Layout, Rect},
+        style::{Style, Stylize},
+        text::{Line, Text},
+        widgets::{Block, Clear, List, ListItem, ListState, Paragraph, Wrap},
+        DefaultTerminal, Frame,
+    },
+    std::{
+        cell::OnceCell,
+        io::{stdout, Write},
+    },
+};
+
+/// Maximum number of results listed
+const LIMIT: usize = 1000;
+
+/// A search result as displayed in the list
+struct Row {
+    name: String,
+    version: String,
+    description: Option<String>,
+    registry: Option<String>,
+
+    /// Registry and conflicts tag like `[internal; conflicts with panamax]`
+    tag: Option<String>,
+
+    load: Box<dyn Fn() -> Result<Details, String>>,
+    details: OnceCell<Result<Details, String>>,
+}
+
+impl Row {
+    fn line(&self) -> String {
+        format!("{} = \"{}\"", self.name, self.version)
+    }
+}
+
+struct App<'a> {
+    index: &'a Index,
+    case_insensitive: bool,
+    include_yanked: bool,
+    query: String,
+
+    /// Queries of the current results, to only search again when they change
+    queries: Option<Vec<String>>,
+
+    rows: Vec<Row>,
+    state: ListState,
+    status: String,
+    readme: Option<(String, u16)>,
+}
+
+/**
+Interactive search: filter as the query is typed, show details for the selected crate, copy its
+`name = "version"` line, or view its README
+
+Returns the selected line if the user pressed Enter.
+*/
+pub fn run(
+    index: &Index,
+    query: String,
+    case_insensitive: bool,
+    include_yanked: bool,
+) -> Result<Option<String>> {
+    let mut app = App {
+        index,
+        case_insensitive,
+        include_yanked,
+        query,
+        queries: None,
+        rows: vec![],
+        state: ListState::default(),
+        status: String::new(),
+        readme: None,
+    };
+    app.search();
+
+    let mut terminal = ratatui::init();
+    let r = app.run(&mut terminal);
+    ratatui::restore();
+    r
+}
+
+impl App<'_> {
+    fn run(&mut self, terminal: &mut DefaultTerminal) -> Result<Option<String>> {
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+
+            // README view
+            if let Some((_readme, scroll)) = &mut self.readme {
+                match key.code {
+                    KeyCode::Esc | KeyCode::Char('q') => self.readme = None,
+                    KeyCode::Char('c') if ctrl => self.readme = None,
+                    KeyCode::Up => *scroll = scroll.saturating_sub(1),
+                    KeyCode::Down => *scroll = scroll.saturating_add(1),
+                    KeyCode::PageUp => *scroll = scroll.saturating_sub(20),
+                    KeyCode::PageDown => *scroll = scroll.saturating_add(20),
+                    KeyCode::Home => *scroll = 0,
+                    _ => {}
+                }
+                continue;
+            }
+
+            match key.code {
+                KeyCode::Esc => return Ok(None),
+                KeyCode::Char('c') if ctrl => return Ok(None),
+                KeyCode::Enter => {
+                    if let Some(row) = self.selected() {
+                        let line = row.line();
+                        copy(&line)?;
+                        return Ok(Some(line));
+                    }
+                }
+                KeyCode::Char('y') if ctrl => {
+                    if let Some(row) = self.selected() {
+                        let line = row.line();
+                        copy(&line)?;
+                        self.status = format!("Copied {line}");
+                    }
+                }
+                KeyCode::Char('r') if ctrl => self.open_readme(),
+                KeyCode::Up => self.state.select_previous(),
+                KeyCode::Down => self.state.select_next(),
+                KeyCode::PageUp => self.state.scroll_up_by(20),
+                KeyCode::PageDown => self.state.scroll_down_by(20),
+                KeyCode::Backspace => {
+                    self.query.pop();
+                    self.search();
+                }
+                KeyCode::Char(c) if !ctrl => {
+                    self.query.push(c);
+                    self.search();
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn selected(&self) -> Option<&Row> {
+        self.state.selected().and_then(|i| self.rows.get(i))
+    }
+
+    fn search(&mut self) {
+        let queries = self
+            .query
+            .split_whitespace()
+            .map(String::from)
+            .collect::<Vec<_>>();
+
+        // E.g. a trailing space doesn't change the results
+        if self.queries.as_ref() == Some(&queries) {
+            return;
+        }
+        self.queries = Some(queries.clone());
+
+        self.status.clear();
+        self.rows.clear();
+
+        if queries.is_empty() {
+            self.state.select(None);
+            return;
+        }
+
+        // Don't search while the user is in the middle of typing a regex
+        let results = match self
+            .index
+            .search_builder(&queries)
+            .case_insensitive(self.case_insensitive)
+            .build()
+        {
+            Ok(results) => results,
+            Err(e) => {
+                self.status = e.to_string().replace('\n', " ");
+                self.state.select(None);
+                return;
+            }
+        };
+
+        // One row per mirror for crates in more than one mirror
+        self.status = format!("{} crates", results.count());
+        self.rows = results
+            .iter()
+            .flat_map(|crate_| crate_.tagged())
+            .take(LIMIT)
+            .map(|(crate_, tag)| {
+                let crate_ = crate_.clone();
+                Row {
+                    name: crate_.name.clone(),
+                    version: crate_.version(self.include_yanked).to_string(),
+                    description: crate_.description.clone(),
+                    registry: crate_.registry.clone(),
+                    tag,
+                    details: OnceCell::new(),
+                    // Details are read from the mirror only when the crate is selected
+                    load: Box::new(move || crate_.details().map_err(|e| e.to_string())),
+                }
+            })
+            .collect();
+
+        self.state.select((!self.rows.is_empty()).then_some(0));
+    }
+
+    fn selected_details(&self) -> Option<&Result<Details, String>> {
+        self.selected()
+            .map(|row| row.details.get_or_init(|| (row.load)()))
+    }
+
+    fn open_readme(&mut self) {
+        match self.selected_details() {
+            Some(Ok(Details {
+                readme: Some(readme),
+                ..
+            })) => {
+                self.readme = Some((readme.clone(), 0));
+            }
+            Some(Ok(_)) => self.status = String::from("No README"),
+            Some(Err(e)) => self.status = e.clone(),
+            None => {}
+        }
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [input, main, help] = Layout::vertical([
+            Constraint::Length(3),
+            Constraint::Min(0),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+        let [list, detail] =
+            Layout::horizontal([Constraint::Percentage(50), Constraint::Percentage(50)])
+                .areas(main);
+
+        let title = if self.status.is_empty() {
+            String::from(" Search ")
+        } else {
+            format!(" Search ({}) ", self.status)
+        };
+        frame.render_widget(
+            Paragraph::new(format!("{}▏", self.query)).block(Block::bordered().title(title)),
+            input,
+        );
+
+        let items = self
+            .rows
+            .iter()
+            .map(|row| {
+                let mut line = Line::from(vec![
+                    row.name.clone().green(),
+                    format!(" {}", row.version).into(),
+                ]);
+                if let Some(tag) = &row.tag {
+                    line.push_span(format!(" {tag}").dim());
+                }
+                ListItem::new(line)
+            })
+            .collect::<Vec<_>>();
+        frame.render_stateful_widget(
+            List::new(items)
+                .block(Block::bordered().title(" Crates "))
+                .highlight_style(Style::new().reversed()),
+            list,
+            &mut self.state,
+        );
+
+        let text = self.detail_text();
+        frame.render_widget(
+            Paragraph::new(text)
+                .wrap(Wrap { trim: false })
+                .block(Block::bordered().title(" Details ")),
+            detail,
+        );
+
+        frame.render_widget(
+            Line::from(
+                " Type to search · ↑/↓ select · Enter copy and quit · Ctrl-Y copy · Ctrl-R README · Esc quit",
+            )
+            .dim(),
+            help,
+        );
+
+        if let Some((readme, scroll)) = &self.readme {
+            let area = popup(frame.area());
+            frame.render_widget(Clear, area);
+            frame.render_widget(
+                Paragraph::new(readme.as_str())
+                    .wrap(Wrap { trim: false })
+                    .scroll((*scroll, 0))
+                    .block(Block::bordered().title(" README (Esc to close) ")),
+                area,
+            );
+        }
+    }
+
+    fn detail_text(&self) -> Text<'static> {
+        let Some(row) = self.selected() else {
+            return Text::default();
+        };
+
+        let mut lines = vec![
+            Line::from(row.line()).bold(),
+            Line::default(),
+            Line::from(row.description.clone().unwrap_or_default()),
+            Line::default(),
+        ];
+        if let Some(registry) = &row.registry {
+            lines.push(Line::from(format!("Registry: {registry}")));
+        }
+
+        match self.selected_details() {
+            Some(Ok(details)) => lines.extend(
+                details
+                    .to_string()
+                    .lines()
+                    .map(|x| Line::from(x.to_string())),
+            ),
+            Some(Err(e)) => lines.push(Line::from(e.clone()).red()),
+            None => {}
+        }
+
+        Text::from(lines)
+    }
+}
+
+/// Centered area covering most of the screen
+fn popup(area: Rect) -> Rect {
+    let [area] = Layout::vertical([Constraint::Percentage(90)])
+        .flex(ratatui::layout::Flex::Center)
+        .areas(area);
+    let [area] = Layout::horizontal([Constraint::Percentage(90)])
+        .flex(ratatui::layout::Flex::Center)
+        .areas(area);
+    area
+}
+
+/// Copy text to the clipboard via the terminal's OSC 52 escape sequence
+fn copy(s: &str) -> Result<()> {
+    let mut stdout = stdout();
+    write!(stdout, "\x1b]52;c;{}\x07", base64(s.as_bytes()))?;
+    stdout.flush()?;
+    Ok(())
+}
+
+fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut r = String::new();
+    for chunk in data.chunks(3) {
+        let b = [
+            chunk[0],
+            chunk.get(1).copied().unwrap_or(0),
+            chunk.get(2).copied().unwrap_or(0),
+        ];
+        let n = (u32::from(b[0]) << 16) | (u32::from(b[1]) << 8) | u32::from(b[2]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                r.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                r.push('=');
+            }
+        }
+    }
+    r
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn base64_padding() {
+        for (data, encoded) in [
+            ("", ""),
+            ("f", "Zg=="),
+            ("fo", "Zm8="),
+            ("foo", "Zm9v"),
+            ("foob", "Zm9vYg=="),
+            ("fooba", "Zm9vYmE="),
+            ("foobar", "Zm9vYmFy"),
+        ] {
+            assert_eq!(base64(data.as_bytes()), encoded);
+        }
+        assert_eq!(base64(&[0xfb, 0xff]), "+/8=");
+    }
+}
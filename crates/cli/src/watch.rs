@@ -0,0 +1,135 @@
+use {
+    crate::mirror::Mirror,
+    anyhow::{anyhow, Result},
+    clap::Args,
+    log::*,
+    notify::{recommended_watcher, Event, RecursiveMode, Watcher},
+    panamax_search_lib::{DirectorySource, Index},
+    std::{
+        collections::BTreeSet,
+        mem::take,
+        path::Path,
+        sync::mpsc::{channel, RecvTimeoutError},
+        time::Duration,
+    },
+};
+
+#[derive(Args)]
+pub struct Watch {
+    /// Seconds without changes to wait for before updating, so a sync in progress is only
+    /// processed once
+    #[arg(long, value_name = "SECONDS", default_value_t = 5)]
+    debounce: u64,
+
+    /// Also write a static HTML page of new crates and versions next to the Atom feed
+    #[arg(long)]
+    feed_html: bool,
+}
+
+/// Changes to a mirror since its last update
+#[derive(Default)]
+struct Pending {
+    changed: bool,
+
+    /// Index file names, i.e. lowercase crate names
+    names: BTreeSet<String>,
+}
+
+impl Watch {
+    /**
+    Watch each mirror's index directory and `config.json`, and after each burst of changes, re-read
+    only the changed index files into the cache file, update the feed, and print a summary
+    */
+    pub fn run(&self, mirrors: &[Mirror]) -> Result<()> {
+        let (tx, rx) = channel::<notify::Result<Event>>();
+        let mut watcher = recommended_watcher(tx)?;
+
+        let sources = mirrors
+            .iter()
+            .map(|x| DirectorySource::new(&x.path))
+            .collect::<Vec<_>>();
+        for (mirror, source) in mirrors.iter().zip(&sources) {
+            // Start from a fresh cache file
+            mirror.load()?;
+
+            watcher.watch(source.index_directory(), RecursiveMode::Recursive)?;
+            // A sparse index's `config.json` may be outside of the index directory
+            if !source.config_file().starts_with(source.index_directory()) {
+                watcher.watch(source.config_file(), RecursiveMode::NonRecursive)?;
+            }
+            info!("Watch {:?}", mirror.path);
+        }
+
+        let debounce = Duration::from_secs(self.debounce);
+        let mut pending = mirrors
+            .iter()
+            .map(|_| Pending::default())
+            .collect::<Vec<_>>();
+        loop {
+            let event = if pending.iter().any(|x| x.changed) {
+                rx.recv_timeout(debounce)
+            } else {
+                rx.recv().map_err(|_| RecvTimeoutError::Disconnected)
+            };
+
+            match event {
+                Ok(Ok(event)) => {
+                    // Ignore reads, e.g. by the update itself
+                    if event.kind.is_access() {
+                        continue;
+                    }
+                    for path in &event.paths {
+                        for (source, pending) in sources.iter().zip(&mut pending) {
+                            if let Some(name) = index_file_name(source, path) {
+                                pending.names.insert(name);
+                                pending.changed = true;
+                            } else if path == source.config_file() {
+                                pending.changed = true;
+                            }
+                        }
+                    }
+                }
+                Ok(Err(e)) => warn!("{e}"),
+                Err(RecvTimeoutError::Timeout) => {
+                    for (mirror, pending) in mirrors.iter().zip(&mut pending) {
+                        if take(&mut pending.changed) {
+                            let names = take(&mut pending.names).into_iter().collect::<Vec<_>>();
+                            // Keep watching if an update fails, e.g. during an unusual sync
+                            if let Err(e) = self.update(mirror, &names) {
+                                error!("{}: {e}", mirror.name);
+                            }
+                        }
+                    }
+                }
+                Err(RecvTimeoutError::Disconnected) => {
+                    return Err(anyhow!("File watcher stopped"));
+                }
+            }
+        }
+    }
+
+    fn update(&self, mirror: &Mirror, names: &[String]) -> Result<()> {
+        info!("{}: {} changed index files", mirror.name, names.len());
+        let index = Index::rebuild_crates(&mirror.path, &mirror.cache, names)?;
+        let diff = mirror.update_feed(&index, self.feed_html)?;
+        println!("{}: {}", mirror.name, diff.summary());
+        Ok(())
+    }
+}
+
+/// File name of an index file in a mirror's index directory, ignoring hidden files like `.git`
+fn index_file_name(source: &DirectorySource, path: &Path) -> Option<String> {
+    // New prefix directories look like index files
+    if path.is_dir() {
+        return None;
+    }
+    let path = path.strip_prefix(source.index_directory()).ok()?;
+    let components = path
+        .components()
+        .map(|x| x.as_os_str().to_str())
+        .collect::<Option<Vec<_>>>()?;
+    if components.len() < 2 || components.iter().any(|x| x.starts_with('.')) {
+        return None;
+    }
+    components.last().map(|x| x.to_string())
+}
@@ -0,0 +1,373 @@
+use {
+    anyhow::{anyhow, Result},
+    log::*,
+    std::{
+        fs::read_to_string,
+        path::{Path, PathBuf},
+    },
+    toml::{Table, Value},
+};
+
+/**
+Cargo's configuration files, nearest first
+
+```text
+./.cargo/config.toml
+../.cargo/config.toml
+...
+$CARGO_HOME/config.toml
+```
+
+Each table is kept with the directory that relative paths in it are resolved against, the parent of
+the directory containing the file, like cargo.
+*/
+pub struct CargoConfig(Vec<(PathBuf, Table)>);
+
+impl CargoConfig {
+    pub fn load(current_directory: &Path, cargo_home: &Path) -> Result<CargoConfig> {
+        let mut files = current_directory
+            .ancestors()
+            .map(|x| x.join(".cargo"))
+            .collect::<Vec<_>>();
+        if !files.contains(&cargo_home.to_path_buf()) {
+            files.push(cargo_home.to_path_buf());
+        }
+
+        let mut r = vec![];
+        for directory in files {
+            // Cargo also reads the legacy `config` file without an extension
+            let Some(file) = ["config.toml", "config"]
+                .into_iter()
+                .map(|x| directory.join(x))
+                .find(|x| x.is_file())
+            else {
+                continue;
+            };
+            debug!("Load cargo config file {file:?}");
+            let table = read_to_string(&file)?
+                .parse::<Table>()
+                .map_err(|e| anyhow!("{file:?}: {e}"))?;
+            let root = directory.parent().unwrap_or(&directory).to_path_buf();
+            r.push((root, table));
+        }
+
+        Ok(CargoConfig(r))
+    }
+
+    /// Value of a dotted key from the nearest file that has it, with that file's root directory
+    fn get(&self, key: &[&str]) -> Option<(&Path, &Value)> {
+        self.0.iter().find_map(|(root, table)| {
+            let (last, path) = key.split_last()?;
+            let mut table = table;
+            for x in path {
+                table = table.get(*x)?.as_table()?;
+            }
+            Some((root.as_path(), table.get(*last)?))
+        })
+    }
+
+    fn get_str(&self, key: &[&str]) -> Option<&str> {
+        self.get(key).and_then(|x| x.1.as_str())
+    }
+
+    /// Path value of a dotted key, relative to the root directory of the file that has it
+    fn get_path(&self, key: &[&str]) -> Option<PathBuf> {
+        let (root, value) = self.get(key)?;
+        value.as_str().map(|x| root.join(x))
+    }
+
+    /**
+    Mirror directory of a registry (default is `registry.default` or crates.io), following source
+    replacement
+
+    ```toml
+    [source.crates-io]
+    replace-with = "panamax"
+
+    [source.panamax]
+    registry = "file:///srv/panamax/crates.io-index"
+    ```
+
+    Relative `local-registry` and `directory` paths are resolved like cargo does. Fails if the
+    registry isn't replaced by or doesn't point at a local index, e.g. if the mirror is only known
+    by its HTTP URL, or if the source is of a kind without an index, e.g. a git repository.
+    */
+    pub fn mirror_directory(&self, registry: Option<&str>) -> Result<PathBuf> {
+        let registry = registry
+            .or(self.get_str(&["registry", "default"]))
+            .unwrap_or("crates-io");
+
+        // Follow the chain of replacements
+        let mut source = registry;
+        let mut seen = vec![source];
+        while let Some(replacement) = self.get_str(&["source", source, "replace-with"]) {
+            if seen.contains(&replacement) {
+                return Err(anyhow!(
+                    "Cycle in cargo source replacement: {} -> {replacement}",
+                    seen.join(" -> ")
+                ));
+            }
+            seen.push(replacement);
+            source = replacement;
+        }
+        debug!("Registry {registry:?} is replaced by source {source:?}");
+
+        if let Some(url) = self.get_str(&["source", source, "registry"]) {
+            CargoConfig::index_url_directory(url)
+        } else if let Some(path) = self
+            .get_path(&["source", source, "local-registry"])
+            .or_else(|| self.get_path(&["source", source, "directory"]))
+        {
+            Ok(path)
+        } else if let Some(url) = self.get_str(&["registries", source, "index"]) {
+            CargoConfig::index_url_directory(url)
+        } else if let Some((_, Value::Table(table))) = self.get(&["source", source]) {
+            let keys = table
+                .keys()
+                .filter(|x| *x != "replace-with")
+                .map(String::as_str)
+                .collect::<Vec<_>>();
+            Err(anyhow!(
+                "Source {source:?} is not supported: expected `registry`, `local-registry`, or \
+                 `directory`, found `{}`",
+                keys.join("`, `")
+            ))
+        } else {
+            Err(anyhow!(
+                "Registry {registry:?} is not replaced by a local mirror in cargo's configuration"
+            ))
+        }
+    }
+
+    /**
+    Mirror directory of a local index URL
+
+    ```text
+    file:///srv/panamax/crates.io-index => /srv/panamax
+    sparse+file:///srv/panamax/index/   => /srv/panamax
+    file:///srv/registry                => /srv/registry
+    ```
+    */
+    pub fn index_url_directory(url: &str) -> Result<PathBuf> {
+        let Some(path) = url
+            .trim_start_matches("sparse+")
+            .trim_start_matches("registry+")
+            .strip_prefix("file://")
+        else {
+            return Err(anyhow!("Index {url} is not a local directory"));
+        };
+
+        let path = PathBuf::from(path.trim_end_matches('/'));
+        match path.file_name().and_then(|x| x.to_str()) {
+            Some("crates.io-index" | "index") => Ok(path.parent().unwrap().to_path_buf()),
+            _ => Ok(path),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        std::fs::{create_dir_all, write},
+        tempfile::TempDir,
+    };
+
+    fn config(files: &[&str]) -> CargoConfig {
+        CargoConfig(
+            files
+                .iter()
+                .map(|x| (PathBuf::from("/project"), x.parse::<Table>().unwrap()))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn source_replacement() {
+        let r = config(&[r#"
+            [source.crates-io]
+            replace-with = "vendored"
+
+            [source.vendored]
+            replace-with = "panamax"
+
+            [source.panamax]
+            registry = "file:///srv/panamax/crates.io-index"
+
+            [source.local]
+            local-registry = "/srv/local"
+
+            [registries.internal]
+            index = "sparse+file:///srv/registry/index/"
+        "#]);
+        assert_eq!(
+            r.mirror_directory(None).unwrap(),
+            PathBuf::from("/srv/panamax")
+        );
+        assert_eq!(
+            r.mirror_directory(Some("local")).unwrap(),
+            PathBuf::from("/srv/local")
+        );
+        assert_eq!(
+            r.mirror_directory(Some("internal")).unwrap(),
+            PathBuf::from("/srv/registry")
+        );
+        assert!(r.mirror_directory(Some("missing")).is_err());
+    }
+
+    #[test]
+    fn default_registry_and_nearest_file() {
+        let r = config(&[
+            r#"
+            registry.default = "internal"
+
+            [source.internal]
+            directory = "/srv/vendor"
+            "#,
+            r#"
+            [source.crates-io]
+            replace-with = "panamax"
+
+            [source.panamax]
+            registry = "file:///srv/panamax/crates.io-index"
+
+            [source.internal]
+            directory = "/srv/ignored"
+            "#,
+        ]);
+        assert_eq!(
+            r.mirror_directory(None).unwrap(),
+            PathBuf::from("/srv/vendor")
+        );
+        assert_eq!(
+            r.mirror_directory(Some("crates-io")).unwrap(),
+            PathBuf::from("/srv/panamax")
+        );
+    }
+
+    #[test]
+    fn unreplaced_and_cyclic_sources() {
+        assert!(config(&[]).mirror_directory(None).is_err());
+        assert!(config(&[r#"
+            [source.crates-io]
+            replace-with = "mirror"
+
+            [source.mirror]
+            registry = "https://example.com/index"
+        "#])
+        .mirror_directory(None)
+        .is_err());
+
+        let e = config(&[r#"
+            [source.crates-io]
+            replace-with = "a"
+
+            [source.a]
+            replace-with = "crates-io"
+        "#])
+        .mirror_directory(None)
+        .unwrap_err();
+        assert!(e.to_string().contains("crates-io -> a -> crates-io"), "{e}");
+    }
+
+    #[test]
+    fn relative_paths() {
+        let r = config(&[r#"
+            [source.local]
+            local-registry = "registry"
+
+            [source.vendored]
+            directory = "../vendor"
+        "#]);
+        assert_eq!(
+            r.mirror_directory(Some("local")).unwrap(),
+            PathBuf::from("/project/registry")
+        );
+        assert_eq!(
+            r.mirror_directory(Some("vendored")).unwrap(),
+            PathBuf::from("/project/../vendor")
+        );
+
+        let dir = TempDir::new().unwrap();
+        let project = dir.path().join("project");
+        create_dir_all(project.join(".cargo")).unwrap();
+        write(
+            project.join(".cargo").join("config.toml"),
+            "[source.crates-io]\nreplace-with = \"vendored\"\n\
+             [source.vendored]\ndirectory = \"vendor\"\n",
+        )
+        .unwrap();
+        let r = CargoConfig::load(&project.join("src"), &dir.path().join("cargo-home")).unwrap();
+        assert_eq!(r.mirror_directory(None).unwrap(), project.join("vendor"));
+    }
+
+    #[test]
+    fn unsupported_source_kind() {
+        let e = config(&[r#"
+            [source.crates-io]
+            replace-with = "git"
+
+            [source.git]
+            git = "https://example.com/crates.git"
+            branch = "main"
+        "#])
+        .mirror_directory(None)
+        .unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "Source \"git\" is not supported: expected `registry`, `local-registry`, or \
+             `directory`, found `branch`, `git`"
+        );
+    }
+
+    #[test]
+    fn load_nearest_first() {
+        let dir = TempDir::new().unwrap();
+        let project = dir.path().join("project");
+        let cargo_home = dir.path().join("cargo-home");
+        create_dir_all(project.join(".cargo")).unwrap();
+        create_dir_all(&cargo_home).unwrap();
+        write(
+            project.join(".cargo").join("config"),
+            "[source.crates-io]\nreplace-with = \"project\"\n\
+             [source.project]\ndirectory = \"/srv/project\"\n",
+        )
+        .unwrap();
+        write(
+            cargo_home.join("config.toml"),
+            "[source.crates-io]\nreplace-with = \"home\"\n\
+             [source.home]\ndirectory = \"/srv/home\"\n",
+        )
+        .unwrap();
+
+        let r = CargoConfig::load(&project, &cargo_home).unwrap();
+        assert_eq!(
+            r.mirror_directory(None).unwrap(),
+            PathBuf::from("/srv/project")
+        );
+        let r = CargoConfig::load(dir.path(), &cargo_home).unwrap();
+        assert_eq!(
+            r.mirror_directory(None).unwrap(),
+            PathBuf::from("/srv/home")
+        );
+    }
+
+    #[test]
+    fn index_url_directories() {
+        for (url, directory) in [
+            ("file:///srv/panamax/crates.io-index", "/srv/panamax"),
+            (
+                "registry+file:///srv/panamax/crates.io-index/",
+                "/srv/panamax",
+            ),
+            ("sparse+file:///srv/panamax/index/", "/srv/panamax"),
+            ("file:///srv/registry", "/srv/registry"),
+        ] {
+            assert_eq!(
+                CargoConfig::index_url_directory(url).unwrap(),
+                PathBuf::from(directory)
+            );
+        }
+        assert!(CargoConfig::index_url_directory("https://index.crates.io/").is_err());
+    }
+}
@@ -0,0 +1,195 @@
+use {
+    crate::krate::Crate,
+    anyhow::Result,
+    semver::Version,
+    serde::{Deserialize, Serialize},
+    std::{collections::BTreeMap, fmt},
+};
+
+/**
+Outcome of reading a crate's description from the `Cargo.toml` in its crate file
+
+Statuses other than [`DescriptionStatus::Ok`] are kept in the cache file, so a mirror's quirks can
+be tracked over time via [`DescriptionReport`].
+*/
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum DescriptionStatus {
+    /// Read from the `[package]` section of `Cargo.toml`
+    #[default]
+    Ok,
+
+    /// Read from `cargo.toml` instead of `Cargo.toml`
+    LowercaseManifest,
+
+    /// Read from a `[project]` section instead of `[package]`
+    ProjectSection,
+
+    /// `Cargo.toml` has no `description`
+    NoDescription,
+
+    /// The mirror doesn't have the crate file, or it can't be opened
+    MissingCrateFile,
+
+    /// The crate file isn't a gzipped tarball
+    CorruptCrateFile,
+
+    /// The crate file has zero entries
+    EmptyCrateFile,
+
+    /// The crate file doesn't contain a `Cargo.toml`
+    NoManifest,
+
+    /// `Cargo.toml` isn't valid TOML with a `[package]` or `[project]` section
+    MalformedManifest,
+
+    /// Not read yet, or loaded from a cache file saved before statuses were recorded
+    Unknown,
+}
+
+impl DescriptionStatus {
+    pub fn is_ok(&self) -> bool {
+        *self == DescriptionStatus::Ok
+    }
+
+    /// Whether the crate has a description despite the issue
+    pub fn has_description(&self) -> bool {
+        matches!(
+            self,
+            DescriptionStatus::Ok
+                | DescriptionStatus::LowercaseManifest
+                | DescriptionStatus::ProjectSection
+        )
+    }
+}
+
+impl fmt::Display for DescriptionStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            DescriptionStatus::Ok => "Has a description",
+            DescriptionStatus::LowercaseManifest => "Has cargo.toml instead of Cargo.toml",
+            DescriptionStatus::ProjectSection => "Has [project] instead of [package]",
+            DescriptionStatus::NoDescription => "No description",
+            DescriptionStatus::MissingCrateFile => "Missing crate file",
+            DescriptionStatus::CorruptCrateFile => "Corrupt crate file",
+            DescriptionStatus::EmptyCrateFile => "Zero entries in crate file",
+            DescriptionStatus::NoManifest => "No Cargo.toml",
+            DescriptionStatus::MalformedManifest => "Malformed Cargo.toml",
+            DescriptionStatus::Unknown => "Unknown (rebuild the cache file)",
+        })
+    }
+}
+
+/// Crates grouped by their [`DescriptionStatus`], for tracking a mirror's quality
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct DescriptionReport {
+    pub crates: usize,
+
+    /// Crates with a description, including those with a fixed quirk
+    pub descriptions: usize,
+
+    /// Affected crates for each status other than [`DescriptionStatus::Ok`], in report order
+    pub statuses: BTreeMap<DescriptionStatus, Vec<AffectedCrate>>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct AffectedCrate {
+    pub name: String,
+    pub version: Option<Version>,
+}
+
+impl DescriptionReport {
+    pub fn new<'a>(crates: impl Iterator<Item = &'a Crate>) -> DescriptionReport {
+        let mut r = DescriptionReport::default();
+        for crate_ in crates {
+            r.crates += 1;
+            if crate_.description.is_some() {
+                r.descriptions += 1;
+            }
+            if !crate_.description_status.is_ok() {
+                r.statuses
+                    .entry(crate_.description_status)
+                    .or_default()
+                    .push(AffectedCrate {
+                        name: crate_.name.clone(),
+                        version: crate_.latest_non_yanked().or(crate_.newest()).cloned(),
+                    });
+            }
+        }
+        r
+    }
+
+    /// Number of crates with a status
+    pub fn count(&self, status: DescriptionStatus) -> usize {
+        match status {
+            DescriptionStatus::Ok => {
+                self.crates - self.statuses.values().map(|x| x.len()).sum::<usize>()
+            }
+            _ => self.statuses.get(&status).map(|x| x.len()).unwrap_or(0),
+        }
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+}
+
+/**
+Text report
+
+```text
+Descriptions: 152065 of 152340 crates
+
+Affected | Issue | Status
+---|---|---
+27 crates | Has cargo.toml instead of Cargo.toml | Fixed
+275 crates | No description | Omit
+
+Has cargo.toml instead of Cargo.toml (27):
+  foo 0.1.0
+
+No description (275):
+  bar 1.0.0
+```
+*/
+impl fmt::Display for DescriptionReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "Descriptions: {} of {} crates",
+            self.descriptions, self.crates
+        )?;
+        if self.statuses.is_empty() {
+            return Ok(());
+        }
+
+        writeln!(f, "\nAffected | Issue | Status\n---|---|---")?;
+        for (status, crates) in &self.statuses {
+            writeln!(
+                f,
+                "{} {} | {status} | {}",
+                crates.len(),
+                if crates.len() == 1 { "crate" } else { "crates" },
+                if status.has_description() {
+                    "Fixed"
+                } else {
+                    "Omit"
+                },
+            )?;
+        }
+
+        for (status, crates) in &self.statuses {
+            writeln!(f, "\n{status} ({}):", crates.len())?;
+            for x in crates {
+                match &x.version {
+                    Some(version) => writeln!(f, "  {} {version}", x.name)?,
+                    None => writeln!(f, "  {}", x.name)?,
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
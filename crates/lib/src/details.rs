@@ -0,0 +1,153 @@
+use {
+    semver::Version,
+    serde::Deserialize,
+    std::{
+        collections::{BTreeMap, BTreeSet},
+        fmt,
+    },
+};
+
+/**
+Details about a crate that aren't kept in the cache file
+
+Read on demand from the crate's index file and crate file via [`crate::Index`]'s crates, so they
+are only available for crates whose mirror directory is known.
+*/
+#[derive(Clone, Debug)]
+pub struct Details {
+    /// All versions, oldest first, with their yanked status
+    pub versions: Vec<(Version, bool)>,
+
+    /// Version the remaining fields describe: the latest non-yanked version if any, otherwise the
+    /// latest version; for cargo's registry directories, the latest downloaded version if the
+    /// latest version hasn't been downloaded
+    pub version: Version,
+
+    pub license: Option<String>,
+    pub rust_version: Option<String>,
+    pub dependencies: Vec<Dependency>,
+
+    /// Features including the implicit features of optional dependencies
+    pub features: BTreeMap<String, Vec<String>>,
+
+    /// Contents of the README file in the crate file
+    pub readme: Option<String>,
+}
+
+/// License, versions, features, and dependencies, one per line, as shown by `info` and the TUI
+impl fmt::Display for Details {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "License: {}", self.license.as_deref().unwrap_or("?"))?;
+        if let Some(rust_version) = &self.rust_version {
+            writeln!(f, "Rust version: {rust_version}")?;
+        }
+
+        let yanked = self.versions.iter().filter(|x| x.1).count();
+        writeln!(
+            f,
+            "Versions: {} ({yanked} yanked): {}",
+            self.versions.len(),
+            self.versions
+                .iter()
+                .rev()
+                .take(10)
+                .map(|(version, yanked)| if *yanked {
+                    format!("{version} (yanked)")
+                } else {
+                    version.to_string()
+                })
+                .collect::<Vec<_>>()
+                .join(", "),
+        )?;
+
+        if !self.features.is_empty() {
+            writeln!(
+                f,
+                "Features: {}",
+                self.features.keys().cloned().collect::<Vec<_>>().join(", "),
+            )?;
+        }
+
+        writeln!(f, "\nDependencies of {}:", self.version)?;
+        for dep in &self.dependencies {
+            let kind = match dep.kind.as_deref() {
+                Some("dev") => " (dev)",
+                Some("build") => " (build)",
+                _ => "",
+            };
+            let optional = if dep.optional { " (optional)" } else { "" };
+            writeln!(
+                f,
+                "  {} {}{kind}{optional}",
+                dep.package.as_ref().unwrap_or(&dep.name),
+                dep.req,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Dependency {
+    pub name: String,
+    pub req: String,
+
+    #[serde(default)]
+    pub optional: bool,
+
+    /// `normal`, `build`, or `dev`
+    #[serde(default)]
+    pub kind: Option<String>,
+
+    #[serde(default)]
+    pub target: Option<String>,
+
+    /// Actual crate name if the dependency is renamed
+    #[serde(default)]
+    pub package: Option<String>,
+}
+
+/// Index file entry with all the fields used by [`Details`]
+#[derive(Deserialize)]
+pub(crate) struct IndexEntry {
+    pub vers: Version,
+    pub yanked: bool,
+
+    #[serde(default)]
+    pub deps: Vec<Dependency>,
+
+    #[serde(default)]
+    pub features: BTreeMap<String, Vec<String>>,
+
+    #[serde(default)]
+    pub features2: BTreeMap<String, Vec<String>>,
+
+    #[serde(default)]
+    pub rust_version: Option<String>,
+}
+
+impl IndexEntry {
+    /**
+    Merge `features` and `features2` and add the implicit feature of each optional dependency that
+    isn't referenced with `dep:` syntax
+    */
+    pub fn all_features(&self) -> BTreeMap<String, Vec<String>> {
+        let mut r = self.features.clone();
+        r.extend(self.features2.clone());
+
+        let explicit = r
+            .values()
+            .flatten()
+            .filter_map(|x| x.strip_prefix("dep:"))
+            .map(String::from)
+            .collect::<BTreeSet<_>>();
+
+        for dep in self.deps.iter().filter(|x| x.optional) {
+            if !explicit.contains(&dep.name) && !r.contains_key(&dep.name) {
+                r.insert(dep.name.clone(), vec![format!("dep:{}", dep.name)]);
+            }
+        }
+
+        r
+    }
+}
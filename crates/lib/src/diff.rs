@@ -0,0 +1,289 @@
+use {
+    crate::krate::Crate,
+    anyhow::Result,
+    semver::Version,
+    serde::Serialize,
+    std::{collections::BTreeMap, fmt},
+};
+
+/// Changes between two builds of an index
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct Diff {
+    pub new_crates: Vec<NewCrate>,
+    pub new_versions: Vec<NewVersions>,
+    pub newly_yanked: Vec<Yanked>,
+    pub removed_crates: Vec<RemovedCrate>,
+    pub changed_descriptions: Vec<ChangedDescription>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct NewCrate {
+    pub name: String,
+    pub version: Option<Version>,
+    pub description: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct NewVersions {
+    pub name: String,
+    pub from: Option<Version>,
+    pub to: Option<Version>,
+
+    /// Number of new versions; `None` if the previous cache file predates version counts
+    pub count: Option<usize>,
+
+    pub description: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Yanked {
+    pub name: String,
+
+    /// Latest version, if it was newly yanked
+    pub latest: Option<Version>,
+
+    /// Number of newly yanked versions; `None` if the previous cache file predates yanked counts
+    pub count: Option<usize>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct RemovedCrate {
+    pub name: String,
+    pub version: Option<Version>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ChangedDescription {
+    pub name: String,
+    pub from: Option<String>,
+    pub to: Option<String>,
+}
+
+impl Diff {
+    pub fn new(previous: &BTreeMap<String, Crate>, current: &BTreeMap<String, Crate>) -> Diff {
+        let mut r = Diff::default();
+
+        for (name, crate_) in current {
+            let Some(prev) = previous.get(name) else {
+                r.new_crates.push(NewCrate {
+                    name: name.clone(),
+                    version: crate_.newest().cloned(),
+                    description: crate_.description.clone(),
+                });
+                continue;
+            };
+
+            let count = (prev.versions > 0 && crate_.versions > 0)
+                .then(|| crate_.versions.saturating_sub(prev.versions));
+            if crate_.newest() != prev.newest() || count.is_some_and(|x| x > 0) {
+                r.new_versions.push(NewVersions {
+                    name: name.clone(),
+                    from: prev.newest().cloned(),
+                    to: crate_.newest().cloned(),
+                    count,
+                    description: crate_.description.clone(),
+                });
+            }
+
+            // Only counts are kept, so older yanked versions can't be named
+            let latest = crate_
+                .latest
+                .as_ref()
+                .filter(|x| prev.latest.as_ref() != Some(*x))
+                .cloned();
+            let count = (prev.versions > 0 && crate_.versions > 0)
+                .then(|| crate_.yanked.saturating_sub(prev.yanked));
+            if latest.is_some() || count.is_some_and(|x| x > 0) {
+                r.newly_yanked.push(Yanked {
+                    name: name.clone(),
+                    latest,
+                    count,
+                });
+            }
+
+            if crate_.description != prev.description {
+                r.changed_descriptions.push(ChangedDescription {
+                    name: name.clone(),
+                    from: prev.description.clone(),
+                    to: crate_.description.clone(),
+                });
+            }
+        }
+
+        for (name, crate_) in previous {
+            if !current.contains_key(name) {
+                r.removed_crates.push(RemovedCrate {
+                    name: name.clone(),
+                    version: crate_.newest().cloned(),
+                });
+            }
+        }
+
+        r
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /**
+    Counts of each kind of change, omitting zeros
+
+    ```text
+    2 new crates, 5 new versions, 1 newly yanked
+    ```
+    */
+    pub fn summary(&self) -> String {
+        let counts = [
+            (self.new_crates.len(), "new crate", "new crates"),
+            (self.new_versions.len(), "new version", "new versions"),
+            (self.newly_yanked.len(), "newly yanked", "newly yanked"),
+            (self.removed_crates.len(), "removed crate", "removed crates"),
+            (
+                self.changed_descriptions.len(),
+                "changed description",
+                "changed descriptions",
+            ),
+        ];
+        let r = counts
+            .iter()
+            .filter(|(n, _one, _many)| *n > 0)
+            .map(|(n, one, many)| format!("{n} {}", if *n == 1 { one } else { many }))
+            .collect::<Vec<_>>();
+        if r.is_empty() {
+            String::from("No changes")
+        } else {
+            r.join(", ")
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.new_crates.is_empty()
+            && self.new_versions.is_empty()
+            && self.newly_yanked.is_empty()
+            && self.removed_crates.is_empty()
+            && self.changed_descriptions.is_empty()
+    }
+}
+
+fn version(version: &Option<Version>) -> String {
+    version
+        .as_ref()
+        .map(|x| x.to_string())
+        .unwrap_or_else(|| String::from("?"))
+}
+
+/**
+Text report
+
+```text
+New crates (1):
+  foo = "0.1.0"    # Description
+
+New versions (1):
+  bar 1.0.0 -> 1.2.0 (+2)
+
+Newly yanked versions (1):
+  baz 0.3.1 (+1)
+
+Removed crates (1):
+  qux 0.1.0
+
+Changed descriptions (1):
+  quux: "Old" -> "New"
+```
+*/
+impl fmt::Display for Diff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut sections = vec![];
+
+        if !self.new_crates.is_empty() {
+            let width = self
+                .new_crates
+                .iter()
+                .map(|x| x.name.len() + version(&x.version).len())
+                .max()
+                .unwrap();
+            let mut s = format!("New crates ({}):\n", self.new_crates.len());
+            for x in &self.new_crates {
+                let nv = format!("{} = \"{}\"", x.name, version(&x.version));
+                match &x.description {
+                    Some(d) => s.push_str(&format!(
+                        "  {nv}{}    # {}\n",
+                        " ".repeat(width - x.name.len() - version(&x.version).len()),
+                        d.replace('\n', "\\n").replace('\r', "\\r"),
+                    )),
+                    None => s.push_str(&format!("  {nv}\n")),
+                }
+            }
+            sections.push(s);
+        }
+
+        if !self.new_versions.is_empty() {
+            let mut s = format!("New versions ({}):\n", self.new_versions.len());
+            for x in &self.new_versions {
+                let count = x.count.map(|n| format!(" (+{n})")).unwrap_or_default();
+                s.push_str(&format!(
+                    "  {} {} -> {}{count}\n",
+                    x.name,
+                    version(&x.from),
+                    version(&x.to),
+                ));
+            }
+            sections.push(s);
+        }
+
+        if !self.newly_yanked.is_empty() {
+            let n = self
+                .newly_yanked
+                .iter()
+                .map(|x| x.count.unwrap_or(1))
+                .sum::<usize>();
+            let mut s = format!("Newly yanked versions ({n}):\n");
+            for x in &self.newly_yanked {
+                let latest = x
+                    .latest
+                    .as_ref()
+                    .map(|v| format!(" {v}"))
+                    .unwrap_or_default();
+                let count = x.count.map(|n| format!(" (+{n})")).unwrap_or_default();
+                s.push_str(&format!("  {}{latest}{count}\n", x.name));
+            }
+            sections.push(s);
+        }
+
+        if !self.removed_crates.is_empty() {
+            let mut s = format!("Removed crates ({}):\n", self.removed_crates.len());
+            for x in &self.removed_crates {
+                s.push_str(&format!("  {} {}\n", x.name, version(&x.version)));
+            }
+            sections.push(s);
+        }
+
+        if !self.changed_descriptions.is_empty() {
+            let mut s = format!(
+                "Changed descriptions ({}):\n",
+                self.changed_descriptions.len(),
+            );
+            let description = |x: &Option<String>| match x {
+                Some(x) => format!("{x:?}"),
+                None => String::from("(none)"),
+            };
+            for x in &self.changed_descriptions {
+                s.push_str(&format!(
+                    "  {}: {} -> {}\n",
+                    x.name,
+                    description(&x.from),
+                    description(&x.to),
+                ));
+            }
+            sections.push(s);
+        }
+
+        if sections.is_empty() {
+            writeln!(f, "No changes")
+        } else {
+            write!(f, "{}", sections.join("\n"))
+        }
+    }
+}
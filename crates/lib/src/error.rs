@@ -0,0 +1,111 @@
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+/// Errors from loading an index from a mirror directory or cache file
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("Directory does not exist {0:?}")]
+    DirectoryNotFound(PathBuf),
+
+    #[error("Directory is not a directory {0:?}")]
+    NotADirectory(PathBuf),
+
+    /// The mirror directory doesn't have an index `config.json`
+    #[error("Index config file does not exist {0:?}")]
+    ConfigFileNotFound(PathBuf),
+
+    #[error("Cache file does not exist {0:?}")]
+    CacheFileNotFound(PathBuf),
+
+    /// The mirror was updated after the cache file was saved
+    #[error("Cache file is old {0:?}")]
+    CacheFileStale(PathBuf),
+
+    #[error("Could not read cache file {path:?}: {source}")]
+    ReadCacheFile { path: PathBuf, source: io::Error },
+
+    #[error("Could not parse cache file {path:?}: {source}")]
+    ParseCacheFile {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+
+    #[error("{path:?}: Could not read index file: {source}")]
+    ReadIndexFile { path: PathBuf, source: io::Error },
+
+    #[error("{0:?}: Empty file")]
+    EmptyIndexFile(PathBuf),
+
+    /// A cargo `.cache` file that isn't in the supported format
+    #[error("{path:?}: {reason}")]
+    InvalidCargoCacheFile { path: PathBuf, reason: String },
+
+    /**
+    An index file line that isn't a valid index entry
+
+    For cargo `.cache` files, the line is the number of the entry.
+    */
+    #[error("{path:?}:{line}: Deserialization error: {source}")]
+    InvalidIndexLine {
+        path: PathBuf,
+        line: usize,
+        source: serde_json::Error,
+    },
+
+    #[error("{0:?}: No versions")]
+    NoVersions(PathBuf),
+
+    /// The git history of a git index couldn't be read, e.g. to find the index files a sync changed
+    #[error("{path:?}: Git error: {source}")]
+    Git {
+        path: PathBuf,
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+
+    /// A compressed tarball whose crate files don't fit in the memory limit of
+    /// [`crate::TarballSource`]
+    #[error(
+        "{path:?}: Crate files exceed {limit} bytes; decompress the tarball to read them on demand"
+    )]
+    TarballTooLarge { path: PathBuf, limit: u64 },
+
+    /// A search query that isn't a valid regular expression
+    #[error("Invalid query: {0}")]
+    InvalidQuery(#[from] regex::Error),
+
+    /// The index has no git HEAD commit to diff the cache file's against, e.g. a sparse index or a
+    /// cache file saved without one
+    #[error("{0:?}: No git HEAD commits to diff")]
+    NoGitHead(PathBuf),
+
+    /// A crate that didn't come from a mirror, so its details can't be read
+    #[error("{0}: Unknown mirror directory")]
+    UnknownMirror(String),
+
+    #[error("{name}: No version {version} in index file")]
+    VersionNotFound { name: String, version: String },
+
+    #[error("Could not serialize cache file: {0}")]
+    Serialize(serde_json::Error),
+
+    /// The index files of a [`crate::MirrorSource`] couldn't be listed
+    #[error("Could not list index files: {0}")]
+    ListIndexFiles(io::Error),
+
+    #[error("{path:?}: {source}")]
+    Io { path: PathBuf, source: io::Error },
+}
+
+impl Error {
+    /// Wrap an [`io::Error`] with the path it's about, for `map_err`
+    pub(crate) fn io(path: &Path) -> impl FnOnce(io::Error) -> Error + '_ {
+        move |source| Error::Io {
+            path: path.to_path_buf(),
+            source,
+        }
+    }
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
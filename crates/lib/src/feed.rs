@@ -0,0 +1,200 @@
+use {
+    crate::{
+        diff::Diff,
+        highlight::{Highlighter, Html},
+    },
+    anyhow::{anyhow, Result},
+    log::info,
+    semver::Version,
+    serde::{Deserialize, Serialize},
+    std::{
+        fs::{read_to_string, write},
+        path::Path,
+        time::SystemTime,
+    },
+};
+
+/// Maximum number of entries kept in the feed
+const MAX_ENTRIES: usize = 500;
+
+/// Newly mirrored crate or version
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct FeedEntry {
+    pub name: String,
+    pub version: Version,
+    pub description: Option<String>,
+
+    /// Previous latest version, or `None` for a new crate
+    pub from: Option<Version>,
+
+    /// RFC 3339 time of the rebuild that found the entry
+    pub updated: String,
+}
+
+/**
+Feed of newly mirrored crates and versions
+
+Entries accumulate across rebuilds in `feed.json` in a directory, e.g. the mirror directory or the
+cache directory, newest first, and are written as an Atom feed (`feed.atom`) and optionally a static
+HTML page (`feed.html`) next to it so they can be served by the same web server as the mirror.
+*/
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct Feed {
+    pub updated: Option<String>,
+    pub entries: Vec<FeedEntry>,
+}
+
+impl Feed {
+    /// Load the feed's entries from `feed.json` in a directory, if any
+    pub fn load(directory: &Path) -> Result<Feed> {
+        let feed_file = directory.join("feed.json");
+        if !feed_file.is_file() {
+            return Ok(Feed::default());
+        }
+        let s = read_to_string(&feed_file)?;
+        serde_json::from_str(&s).map_err(|e| anyhow!("Could not parse {feed_file:?}: {e}"))
+    }
+
+    /// Add the new crates and versions of a diff as entries
+    pub fn update(&mut self, diff: &Diff, time: SystemTime) {
+        let updated = humantime::format_rfc3339_seconds(time).to_string();
+
+        let mut entries = diff
+            .new_crates
+            .iter()
+            .filter_map(|x| {
+                Some(FeedEntry {
+                    name: x.name.clone(),
+                    version: x.version.clone()?,
+                    description: x.description.clone(),
+                    from: None,
+                    updated: updated.clone(),
+                })
+            })
+            .chain(diff.new_versions.iter().filter_map(|x| {
+                Some(FeedEntry {
+                    name: x.name.clone(),
+                    version: x.to.clone()?,
+                    description: x.description.clone(),
+                    from: x.from.clone(),
+                    updated: updated.clone(),
+                })
+            }))
+            .collect::<Vec<_>>();
+        entries.sort_by(|a, b| a.name.cmp(&b.name));
+
+        entries.append(&mut self.entries);
+        entries.truncate(MAX_ENTRIES);
+        self.entries = entries;
+        self.updated = Some(updated);
+    }
+
+    /// Write `feed.json`, `feed.atom`, and optionally `feed.html` to a directory
+    pub fn save(&self, directory: &Path, title: &str, html: bool) -> Result<()> {
+        let feed_file = directory.join("feed.json");
+        info!("Save feed {feed_file:?}");
+        write(&feed_file, serde_json::to_string(self)?)?;
+
+        let atom_file = directory.join("feed.atom");
+        info!("Save Atom feed {atom_file:?}");
+        write(&atom_file, self.to_atom(title))?;
+
+        if html {
+            let html_file = directory.join("feed.html");
+            info!("Save HTML feed {html_file:?}");
+            write(&html_file, self.to_html(title))?;
+        }
+
+        Ok(())
+    }
+
+    /// Atom feed document
+    pub fn to_atom(&self, title: &str) -> String {
+        let updated = self
+            .updated
+            .clone()
+            .unwrap_or_else(|| humantime::format_rfc3339_seconds(SystemTime::now()).to_string());
+
+        let mut r = format!(
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n\
+            <feed xmlns=\"http://www.w3.org/2005/Atom\">\n\
+            \x20 <title>{}</title>\n\
+            \x20 <id>tag:panamax-search,2024:{}</id>\n\
+            \x20 <updated>{updated}</updated>\n\
+            \x20 <author><name>panamax-search</name></author>\n",
+            Html.text(&format!("New crates and versions in {title}")),
+            Html.text(title),
+        );
+
+        for entry in &self.entries {
+            r.push_str(&format!(
+                "  <entry>\n\
+                \x20   <title>{}</title>\n\
+                \x20   <id>tag:panamax-search,2024:{}@{}</id>\n\
+                \x20   <updated>{}</updated>\n\
+                \x20   <summary>{}</summary>\n\
+                \x20 </entry>\n",
+                Html.text(&entry.title()),
+                Html.text(&entry.name),
+                entry.version,
+                entry.updated,
+                Html.text(&entry.summary()),
+            ));
+        }
+
+        r.push_str("</feed>\n");
+        r
+    }
+
+    /// Static HTML page listing the same entries as the Atom feed
+    pub fn to_html(&self, title: &str) -> String {
+        let title = Html.text(&format!("New crates and versions in {title}"));
+
+        let mut r = format!(
+            "<!DOCTYPE html>\n\
+            <html>\n\
+            <head>\n\
+            <meta charset=\"utf-8\">\n\
+            <title>{title}</title>\n\
+            <link rel=\"alternate\" type=\"application/atom+xml\" href=\"feed.atom\">\n\
+            </head>\n\
+            <body>\n\
+            <h1>{title}</h1>\n\
+            <table>\n\
+            <tr><th>Updated</th><th>Crate</th><th>Version</th><th>Description</th></tr>\n",
+        );
+
+        for entry in &self.entries {
+            let version = match &entry.from {
+                Some(from) => format!("{from} &rarr; {}", entry.version),
+                None => format!("{} (new)", entry.version),
+            };
+            r.push_str(&format!(
+                "<tr><td>{}</td><td>{}</td><td>{version}</td><td>{}</td></tr>\n",
+                entry.updated,
+                Html.text(&entry.name),
+                Html.text(entry.description.as_deref().unwrap_or_default()),
+            ));
+        }
+
+        r.push_str("</table>\n</body>\n</html>\n");
+        r
+    }
+}
+
+impl FeedEntry {
+    fn title(&self) -> String {
+        match &self.from {
+            Some(_) => format!("{} {}", self.name, self.version),
+            None => format!("New crate: {} {}", self.name, self.version),
+        }
+    }
+
+    fn summary(&self) -> String {
+        let description = self.description.as_deref().unwrap_or("No description");
+        match &self.from {
+            Some(from) => format!("{description} (previously {from})"),
+            None => description.to_string(),
+        }
+    }
+}
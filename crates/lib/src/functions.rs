@@ -1,35 +1,96 @@
-use {
-    anyhow::{anyhow, Result},
-    log::error,
-    std::path::Path,
-    walkdir::DirEntry,
-};
-
-pub fn path_parent(path: &Path, levels: usize) -> &Path {
-    let mut r = path;
-    for _ in 0..levels {
-        r = r.parent().unwrap();
+use {crate::error::Error, log::error, std::path::Path, walkdir::DirEntry};
+
+/**
+Directory prefix of a crate in the index (and some crate file layouts)
+
+```text
+a    => 1
+aa   => 2
+aaa  => 3/a
+aaaa => aa/aa
+```
+*/
+pub fn prefix(name: &str) -> String {
+    match name.len() {
+        1 => String::from("1"),
+        2 => String::from("2"),
+        3 => format!("3/{}", &name[..1]),
+        _ => format!("{}/{}", &name[..2], &name[2..4]),
     }
-    r
 }
 
 pub fn filter_entries(entry: &DirEntry) -> bool {
+    let hidden = entry.file_name().to_str().unwrap().starts_with('.');
     if entry.file_type().is_dir() {
-        !entry.file_name().to_str().unwrap().starts_with('.')
+        // Allow the root directory to be hidden, i.e. cargo's `.cache` directory
+        entry.depth() == 0 || !hidden
     } else {
-        entry.depth() > 1
+        entry.depth() > 1 && !hidden
+    }
+}
+
+/**
+Parse a cargo index cache file into its JSON entries
+
+```text
+[cache version: u8 = 3][index format version: u32 LE][index file version]\0
+[version]\0[JSON]\0
+[version]\0[JSON]\0
+...
+```
+*/
+pub fn parse_cache_file(path: &Path, data: &[u8]) -> Result<Vec<String>, Error> {
+    let invalid = |reason: &str| Error::InvalidCargoCacheFile {
+        path: path.to_path_buf(),
+        reason: reason.to_string(),
+    };
+    let Some((&3, rest)) = data.split_first() else {
+        return Err(invalid("Unsupported cache file version"));
+    };
+
+    // Skip the index format version and index file version (git commit or HTTP etag)
+    let mut fields = rest
+        .get(4..)
+        .ok_or_else(|| invalid("Truncated cache file"))?
+        .split(|x| *x == 0)
+        .skip(1);
+
+    let mut r = vec![];
+    while let (Some(version), Some(json)) = (fields.next(), fields.next()) {
+        if version.is_empty() {
+            break;
+        }
+        r.push(String::from_utf8(json.to_vec()).map_err(|e| invalid(&e.to_string()))?);
+    }
+
+    Ok(r)
+}
+
+/**
+All JSON entries of an index file, oldest first, whether JSON lines or a cargo cache file; the
+path is only used in errors
+*/
+pub fn index_entries(path: &Path, data: &[u8]) -> Result<Vec<String>, Error> {
+    match data.first() {
+        None => Err(Error::EmptyIndexFile(path.to_path_buf())),
+        Some(b'{') => Ok(String::from_utf8_lossy(data)
+            .lines()
+            .filter(|x| !x.is_empty())
+            .map(String::from)
+            .collect()),
+        Some(_) => parse_cache_file(path, data),
     }
 }
 
-pub fn ensure_directory(directory: &Path) -> Result<()> {
+pub fn ensure_directory(directory: &Path) -> Result<(), Error> {
     if !directory.exists() {
         error!("Directory does not exist {directory:?}");
-        return Err(anyhow!("Directory does not exist {directory:?}"));
+        return Err(Error::DirectoryNotFound(directory.to_path_buf()));
     }
 
     if !directory.is_dir() {
         error!("Directory is not a directory {directory:?}");
-        return Err(anyhow!("Directory is not a directory {directory:?}"));
+        return Err(Error::NotADirectory(directory.to_path_buf()));
     }
 
     Ok(())
@@ -0,0 +1,92 @@
+use {
+    crate::error::{Error, Result},
+    gix::{
+        diff::tree::{recorder::Change, Recorder, State},
+        objs::TreeRefIter,
+        ObjectId,
+    },
+    log::*,
+    std::{collections::BTreeSet, path::Path},
+};
+
+/**
+Commit checked out in a git working tree, read with [gix] without running `git`
+
+Follows symbolic refs, packed refs, and `.git` files pointing to the git directory, like in
+submodules and worktrees. `None` if the directory isn't a git working tree or its HEAD can't be
+resolved, e.g. in an empty repository.
+*/
+pub fn head(work_tree: &Path) -> Option<String> {
+    let r = gix::open(work_tree)
+        .map_err(|e| e.to_string())
+        .and_then(|x| {
+            x.head_id()
+                .map(|x| x.to_string())
+                .map_err(|e| e.to_string())
+        })
+        .inspect_err(|e| debug!("{work_tree:?}: {e}"))
+        .ok();
+    debug!("Git HEAD of {work_tree:?}: {r:?}");
+    r
+}
+
+/**
+File names of the index files added, modified, or deleted between two commits of a git index,
+i.e. the lowercase names of the crates a sync changed
+
+Diffs the commits' trees with [gix] instead of comparing files, so it takes about as long as
+reading the changed trees. Fails if either commit isn't in the repository, e.g. after the index's
+history was squashed.
+*/
+pub fn changed_index_files(work_tree: &Path, old: &str, new: &str) -> Result<Vec<String>> {
+    let git_error = |e: Box<dyn std::error::Error + Send + Sync>| Error::Git {
+        path: work_tree.to_path_buf(),
+        source: e,
+    };
+
+    let repository = gix::open(work_tree).map_err(|e| git_error(e.into()))?;
+    let tree = |commit: &str| {
+        let id = ObjectId::from_hex(commit.as_bytes()).map_err(|e| git_error(e.into()))?;
+        repository
+            .find_commit(id)
+            .and_then(|x| x.tree())
+            .map_err(|e| git_error(e.into()))
+    };
+    let (old, new) = (tree(old)?, tree(new)?);
+
+    let mut recorder = Recorder::default();
+    gix::diff::tree(
+        TreeRefIter::from_bytes(&old.data, old.id.kind()),
+        TreeRefIter::from_bytes(&new.data, new.id.kind()),
+        State::default(),
+        &repository.objects,
+        &mut recorder,
+    )
+    .map_err(|e| git_error(e.into()))?;
+
+    Ok(recorder
+        .records
+        .iter()
+        .filter_map(|change| {
+            let (mode, path) = match change {
+                Change::Addition {
+                    entry_mode, path, ..
+                }
+                | Change::Deletion {
+                    entry_mode, path, ..
+                }
+                | Change::Modification {
+                    entry_mode, path, ..
+                } => (entry_mode, path.to_string()),
+            };
+            // Like `filter_entries`: skip `config.json` and hidden files like `.github`
+            let components = path.split('/').collect::<Vec<_>>();
+            (!mode.is_tree()
+                && components.len() > 1
+                && !components.iter().any(|x| x.starts_with('.')))
+            .then(|| components.last().unwrap().to_string())
+        })
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect())
+}
@@ -0,0 +1,172 @@
+use {
+    owo_colors::{OwoColorize, Style},
+    regex::Regex,
+    std::ops::Range,
+};
+
+/**
+Renders text with highlighted matches
+
+Text outside of matches is passed to [`Highlighter::text`] so that renderers can escape it.
+*/
+pub trait Highlighter: Sync {
+    /// Render text outside of matches
+    fn text(&self, s: &str) -> String {
+        s.to_string()
+    }
+
+    /// Render a match
+    fn highlight(&self, s: &str) -> String;
+}
+
+/// ANSI escape codes for a terminal
+pub struct Ansi(pub Style);
+
+impl Highlighter for Ansi {
+    fn highlight(&self, s: &str) -> String {
+        s.style(self.0).to_string()
+    }
+}
+
+/// HTML with matches in `<mark>` elements
+pub struct Html;
+
+impl Highlighter for Html {
+    fn text(&self, s: &str) -> String {
+        s.replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+            .replace('"', "&quot;")
+    }
+
+    fn highlight(&self, s: &str) -> String {
+        format!("<mark>{}</mark>", self.text(s))
+    }
+}
+
+/// Markdown with matches in bold
+pub struct Markdown;
+
+impl Highlighter for Markdown {
+    fn text(&self, s: &str) -> String {
+        let mut r = String::new();
+        for c in s.chars() {
+            if matches!(c, '\\' | '*' | '_' | '`' | '[' | ']' | '<' | '>') {
+                r.push('\\');
+            }
+            r.push(c);
+        }
+        r
+    }
+
+    fn highlight(&self, s: &str) -> String {
+        format!("**{}**", self.text(s))
+    }
+}
+
+/**
+Byte ranges of all matches of all patterns, sorted and merged so that overlapping and adjacent
+matches become a single range
+
+```text
+patterns: "ab", "bc"
+text:     "xabcx"  => [1..4]
+```
+*/
+pub(crate) fn match_ranges(patterns: &[Regex], s: &str) -> Vec<Range<usize>> {
+    let mut ranges = patterns
+        .iter()
+        .flat_map(|re| re.find_iter(s).map(|m| m.range()))
+        .filter(|x| !x.is_empty())
+        .collect::<Vec<_>>();
+    ranges.sort_by_key(|x| (x.start, x.end));
+
+    let mut r: Vec<Range<usize>> = vec![];
+    for range in ranges {
+        match r.last_mut() {
+            Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+            _ => r.push(range),
+        }
+    }
+    r
+}
+
+/// Render text once with the given sorted, non-overlapping byte ranges highlighted
+pub(crate) fn render(s: &str, ranges: &[Range<usize>], highlighter: &dyn Highlighter) -> String {
+    let mut r = String::new();
+    let mut i = 0;
+    for range in ranges {
+        r.push_str(&highlighter.text(&s[i..range.start]));
+        r.push_str(&highlighter.highlight(&s[range.clone()]));
+        i = range.end;
+    }
+    r.push_str(&highlighter.text(&s[i..]));
+    r
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, regex::RegexBuilder};
+
+    fn patterns(patterns: &[&str], case_insensitive: bool) -> Vec<Regex> {
+        patterns
+            .iter()
+            .map(|x| {
+                RegexBuilder::new(x)
+                    .case_insensitive(case_insensitive)
+                    .build()
+                    .unwrap()
+            })
+            .collect()
+    }
+
+    /// Ranges as `(start, end)`
+    fn bounds(ranges: &[Range<usize>]) -> Vec<(usize, usize)> {
+        ranges.iter().map(|x| (x.start, x.end)).collect()
+    }
+
+    #[test]
+    fn overlapping_ranges_are_merged() {
+        let re = patterns(&["ab", "bc"], false);
+        let ranges = match_ranges(&re, "xabcx");
+        assert_eq!(bounds(&ranges), [(1, 4)]);
+        assert_eq!(render("xabcx", &ranges, &Markdown), "x**abc**x");
+    }
+
+    #[test]
+    fn adjacent_ranges_are_merged() {
+        let re = patterns(&["ab", "cd", "f"], false);
+        let ranges = match_ranges(&re, "abcdef");
+        assert_eq!(bounds(&ranges), [(0, 4), (5, 6)]);
+        assert_eq!(render("abcdef", &ranges, &Markdown), "**abcd**e**f**");
+    }
+
+    #[test]
+    fn case_insensitive_multi_byte() {
+        let re = patterns(&["é", "ß"], true);
+        let s = "CAFÉ straße";
+        let ranges = match_ranges(&re, s);
+        assert_eq!(bounds(&ranges), [(3, 5), (10, 12)]);
+        assert_eq!(render(s, &ranges, &Markdown), "CAF**É** stra**ß**e");
+    }
+
+    #[test]
+    fn escape_codes_are_not_matched_again() {
+        // `32` occurs in the escape code of the highlighted `e`, but is matched in the input only
+        let style = Style::new().green();
+        let re = patterns(&["e", "32"], false);
+        assert_eq!(
+            render("red", &match_ranges(&re, "red"), &Ansi(style)),
+            format!("r{}d", "e".style(style)),
+        );
+
+        // Escape codes already in the input are passed through around matches
+        let s = "\x1b[1mbold\x1b[0m";
+        let re = patterns(&["bold"], false);
+        assert_eq!(bounds(&match_ranges(&re, s)), [(4, 8)]);
+        assert_eq!(
+            render(s, &match_ranges(&re, s), &Ansi(style)),
+            format!("\x1b[1m{}\x1b[0m", "bold".style(style)),
+        );
+    }
+}
@@ -1,10 +1,18 @@
 use {
     crate::{
-        functions::{ensure_directory, filter_entries},
+        description::{DescriptionReport, DescriptionStatus},
+        diff::Diff,
+        error::{Error, Result},
+        functions::ensure_directory,
+        git,
         krate::Crate,
-        search::Search,
+        layout::Layout,
+        progress::{Counter, Phase, Progress},
+        search::{Search, SearchBuilder, SearchOptions, SearchResults},
+        source::{DirectorySource, MirrorSource},
+        stats::Stats,
+        symbol::SymbolSearch,
     },
-    anyhow::{anyhow, Result},
     log::*,
     rayon::prelude::*,
     serde::{
@@ -12,127 +20,735 @@ use {
         Deserialize,
     },
     std::{
-        collections::BTreeMap,
-        fs::{read_to_string, File},
-        io::{BufWriter, Write},
-        path::Path,
+        collections::{BTreeMap, HashMap},
+        fs::{create_dir_all, read_dir, read_to_string, remove_file, rename, File},
+        io::{self, BufWriter, Write},
+        path::{Path, PathBuf},
+        sync::Arc,
     },
-    walkdir::WalkDir,
 };
 
-pub struct Index(BTreeMap<String, Crate>);
+/// Key of the index's git HEAD commit in the cache file, which can't be a crate name
+const HEAD_KEY: &str = "$head";
+
+/**
+File next to the cache file while the cache file has public items, so a full rebuild after the
+cache file couldn't be read still extracts them
+*/
+const SYMBOLS_MARKER_FILE: &str = "search.symbols";
+
+pub struct Index {
+    crates: BTreeMap<String, Crate>,
+
+    /// Git HEAD commit of the index directory the crates were read from
+    head: Option<String>,
+}
 
 impl Index {
     pub fn load(mirror_directory: &Path) -> Result<Index> {
-        if let Ok(index) = Index::load_from_cache_file(mirror_directory) {
-            Ok(index)
-        } else {
-            Index::load_from_mirror_directory(mirror_directory)
+        Index::load_with_cache_directory(mirror_directory, mirror_directory)
+    }
+
+    /**
+    Like [`Index::load`], but keep the cache file in a separate directory, e.g. if the mirror
+    directory is read-only
+
+    The cache directory is created if needed.
+    */
+    pub fn load_with_cache_directory(
+        mirror_directory: &Path,
+        cache_directory: &Path,
+    ) -> Result<Index> {
+        match Index::read_fresh_cache_file(mirror_directory, cache_directory) {
+            Ok(index) => Ok(index),
+            Err(Error::CacheFileStale(_)) => {
+                Index::rebuild_changed(mirror_directory, cache_directory)
+            }
+            Err(_) => Index::rebuild(
+                mirror_directory,
+                cache_directory,
+                Index::had_symbols(cache_directory),
+            ),
+        }
+    }
+
+    /// Whether the last cache file saved in the cache directory had public items, even if it can't
+    /// be read anymore
+    fn had_symbols(cache_directory: &Path) -> bool {
+        let had = cache_directory.join(SYMBOLS_MARKER_FILE).is_file();
+        if had {
+            warn!("Cache file had public items; extracting them again");
         }
+        had
     }
 
     pub fn load_from_cache_file(mirror_directory: &Path) -> Result<Index> {
-        ensure_directory(mirror_directory)?;
+        Index::read_fresh_cache_file(mirror_directory, mirror_directory)
+    }
 
-        let cache_file = mirror_directory.join("search.json");
-        let config_file = mirror_directory.join("crates.io-index").join("config.json");
+    fn read_fresh_cache_file(mirror_directory: &Path, cache_directory: &Path) -> Result<Index> {
+        ensure_directory(mirror_directory)?;
 
-        if cache_file.is_file() && config_file.is_file() {
-            if cache_file.metadata()?.modified()? > config_file.metadata()?.modified()? {
-                info!("Load index from cache file {cache_file:?}");
+        let cache_file = cache_directory.join("search.json");
+        let source = DirectorySource::new(mirror_directory);
+        let layout = &source.layout;
 
-                return match read_to_string(&cache_file) {
-                    Ok(s) => Index::from_json(&s),
-                    Err(e) => {
-                        error!("Could not read cache file {cache_file:?}: {e}");
-                        Err(anyhow!("Could not read cache file {cache_file:?}: {e}"))
-                    }
-                };
-            } else {
-                info!("Cache file is old {cache_file:?}");
-                return Err(anyhow!("Cache file is old {cache_file:?}"));
-            }
+        if !cache_file.is_file() {
+            return Err(Error::CacheFileNotFound(cache_file));
+        }
+        if !layout.config_file.is_file() {
+            return Err(Error::ConfigFileNotFound(layout.config_file.clone()));
+        }
+        // A git index's HEAD changes on every sync, unlike `config.json`, and survives copying
+        let head = layout.head();
+        if head.is_none()
+            && cache_file
+                .metadata()
+                .and_then(|x| x.modified())
+                .map_err(Error::io(&cache_file))?
+                <= layout.modified().map_err(Error::io(mirror_directory))?
+        {
+            info!("Cache file is old {cache_file:?}");
+            return Err(Error::CacheFileStale(cache_file));
         }
 
-        Err(anyhow!("Cannot load index from cache file {cache_file:?}"))
+        info!("Load index from cache file {cache_file:?}");
+        let mut index = Index::read_cache_file(&cache_file).inspect_err(|e| error!("{e}"))?;
+        if head.is_some() && index.head != head {
+            info!(
+                "Cache file is for git HEAD {:?}, not {head:?} {cache_file:?}",
+                index.head
+            );
+            return Err(Error::CacheFileStale(cache_file));
+        }
+        let source: Arc<dyn MirrorSource> = Arc::new(source);
+        for crate_ in index.crates.values_mut() {
+            crate_.source = Some(source.clone());
+        }
+        Ok(index)
     }
 
     pub fn load_from_mirror_directory(mirror_directory: &Path) -> Result<Index> {
+        Index::rebuild(mirror_directory, mirror_directory, false)
+    }
+
+    /**
+    Like [`Index::load_from_mirror_directory`], but also extracts the public items from each crate's
+    source files so they can be searched via [`Index::search_symbols`]
+
+    This parses every `.rs` file in the latest version of every crate, so it is considerably slower.
+    */
+    pub fn load_from_mirror_directory_with_symbols(mirror_directory: &Path) -> Result<Index> {
+        Index::rebuild(mirror_directory, mirror_directory, true)
+    }
+
+    /**
+    Registry index directories in a cargo home directory (`~/.cargo/registry/index`), named by
+    their directory names without the hash suffix
+
+    ```text
+    ~/.cargo/registry/index/index.crates.io-1949cf8c6b5b557f => index.crates.io
+    ```
+
+    Each can be loaded with [`Index::load`] like a mirror directory, which only includes the crates
+    cargo has downloaded to `~/.cargo/registry/cache`.
+    */
+    pub fn cargo_registries(cargo_home: &Path) -> Result<Vec<(String, PathBuf)>> {
+        let index_directory = cargo_home.join("registry").join("index");
+        ensure_directory(&index_directory)?;
+
+        let mut r = read_dir(&index_directory)
+            .map_err(Error::io(&index_directory))?
+            .flatten()
+            .map(|x| x.path())
+            .filter(|x| x.is_dir())
+            .collect::<Vec<_>>();
+        r.sort();
+
+        let names = r
+            .iter()
+            .map(|x| {
+                let name = x.file_name().unwrap().to_string_lossy().to_string();
+                match name.rsplit_once('-') {
+                    Some((name, _hash)) => name.to_string(),
+                    None => name,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        Ok(r.into_iter()
+            .zip(names.iter())
+            .map(|(path, name)| {
+                // Keep the hash suffix if needed to disambiguate
+                if names.iter().filter(|x| *x == name).count() > 1 {
+                    (
+                        path.file_name().unwrap().to_string_lossy().to_string(),
+                        path,
+                    )
+                } else {
+                    (name.clone(), path)
+                }
+            })
+            .collect())
+    }
+
+    /**
+    Load and merge the indexes of every registry in a cargo home directory
+
+    Enables searching everything cargo has ever downloaded without a mirror.
+    See [`Index::cargo_registries`].
+    */
+    pub fn load_from_cargo_home(cargo_home: &Path) -> Result<Index> {
+        let registries = Index::cargo_registries(cargo_home)?;
+
+        if registries.len() == 1 {
+            Index::load(&registries[0].1)
+        } else {
+            Ok(Index::merge(
+                registries
+                    .into_iter()
+                    .map(|(name, path)| Ok((name, Index::load(&path)?)))
+                    .collect::<Result<Vec<_>>>()?,
+            ))
+        }
+    }
+
+    /**
+    Parse the mirror directory and save the cache file to the cache directory, optionally also
+    extracting public items like [`Index::load_from_mirror_directory_with_symbols`]
+
+    The cache directory is created if needed.
+    */
+    pub fn rebuild(
+        mirror_directory: &Path,
+        cache_directory: &Path,
+        symbols: bool,
+    ) -> Result<Index> {
+        Index::rebuild_with_progress(mirror_directory, cache_directory, symbols, &|_| {})
+    }
+
+    /**
+    Like [`Index::rebuild`], but report the progress of each phase to a callback, e.g. to show a
+    progress bar
+
+    ```no_run
+    # use {panamax_search_lib::Index, std::path::Path};
+    # fn main() -> panamax_search_lib::Result<()> {
+    let mirror = Path::new("/srv/panamax");
+    let index = Index::rebuild_with_progress(mirror, mirror, false, &|progress| {
+        if progress.is_finished() {
+            println!("{}: {}", progress.phase, progress.done);
+        }
+    })?;
+    # Ok(())
+    # }
+    ```
+    */
+    pub fn rebuild_with_progress(
+        mirror_directory: &Path,
+        cache_directory: &Path,
+        symbols: bool,
+        progress: &(dyn Fn(Progress) + Sync),
+    ) -> Result<Index> {
         ensure_directory(mirror_directory)?;
 
         info!("Load index from mirror directory {mirror_directory:?}");
-        let index = Index(
-            WalkDir::new(mirror_directory.join("crates.io-index"))
-                .sort_by_file_name()
-                .into_iter()
-                .filter_entry(filter_entries)
-                .flatten()
-                .collect::<Vec<_>>()
-                .into_par_iter()
-                .filter_map(|x| {
-                    let index_file = x.path();
-                    index_file.is_file().then_some(index_file.to_path_buf())
-                })
-                .map(|index_file| {
-                    let mut crate_ = Crate::new(&index_file).unwrap();
-                    trace!("{crate_:?}");
-                    crate_.add_description(&index_file);
-                    (crate_.name.clone(), crate_)
-                })
-                .collect(),
+        let source = DirectorySource::new(mirror_directory);
+        ensure_directory(&source.layout.index_directory)?;
+        let head = source.layout.head();
+        let mut index = Index::from_source_with_progress(Arc::new(source), symbols, progress)?;
+        index.head = head;
+
+        let save = Counter::start(Phase::Save, Some(1), progress);
+        index.save_with_snapshot(cache_directory)?;
+        save.inc();
+
+        Ok(index)
+    }
+
+    /**
+    Like [`Index::rebuild`], but only re-read the index files of the given crates, e.g. those
+    changed by a sync, and take the other crates from the existing cache file
+
+    Names are the file names of the index files, i.e. lowercase crate names. Crates whose index
+    files no longer exist are removed. Public items are extracted for the re-read crates if the
+    cache file has them. Falls back to a full rebuild if the cache file can't be read, which
+    also extracts public items if the last saved cache file had them.
+    */
+    pub fn rebuild_crates(
+        mirror_directory: &Path,
+        cache_directory: &Path,
+        names: &[String],
+    ) -> Result<Index> {
+        ensure_directory(mirror_directory)?;
+
+        let cache_file = cache_directory.join("search.json");
+        match Index::read_cache_file(&cache_file) {
+            Ok(index) => Index::reload_crates(index, mirror_directory, cache_directory, names),
+            Err(e) => {
+                info!("{e}; rebuilding all crates");
+                Index::rebuild(
+                    mirror_directory,
+                    cache_directory,
+                    Index::had_symbols(cache_directory),
+                )
+            }
+        }
+    }
+
+    /**
+    Like [`Index::rebuild_crates`], but find the crates changed by a sync of a git index by diffing
+    the git HEAD commit recorded in the cache file against the current one
+
+    Only the index files that differ between the two commits' trees are re-read, so updating the
+    cache file after a sync takes seconds. Falls back to a full rebuild if the cache file can't be
+    read, the index isn't a git index, or the commits can't be diffed, e.g. after the index's
+    history was squashed; the full rebuild keeps public items if the last saved cache file had
+    them.
+    */
+    pub fn rebuild_changed(mirror_directory: &Path, cache_directory: &Path) -> Result<Index> {
+        ensure_directory(mirror_directory)?;
+
+        let cache_file = cache_directory.join("search.json");
+        let index = match Index::read_cache_file(&cache_file) {
+            Ok(index) => index,
+            Err(e) => {
+                info!("{e}; rebuilding all crates");
+                return Index::rebuild(
+                    mirror_directory,
+                    cache_directory,
+                    Index::had_symbols(cache_directory),
+                );
+            }
+        };
+
+        // Keep public items if the cache file has them, even when rebuilding all crates
+        let symbols = index.has_symbols();
+        let layout = Layout::load(mirror_directory);
+        let names = match (index.head.as_deref(), layout.head()) {
+            (Some(previous), Some(head)) => {
+                info!(
+                    "Diff git HEAD {previous} to {head} {:?}",
+                    layout.index_directory
+                );
+                git::changed_index_files(&layout.index_directory, previous, &head)
+            }
+            _ => Err(Error::NoGitHead(layout.index_directory.clone())),
+        };
+        match names {
+            Ok(names) => Index::reload_crates(index, mirror_directory, cache_directory, &names),
+            Err(e) => {
+                info!("{e}; rebuilding all crates");
+                Index::rebuild(mirror_directory, cache_directory, symbols)
+            }
+        }
+    }
+
+    /// Re-read the given crates into an index read from a cache file and save it
+    fn reload_crates(
+        mut index: Index,
+        mirror_directory: &Path,
+        cache_directory: &Path,
+        names: &[String],
+    ) -> Result<Index> {
+        info!(
+            "Load {} crates from mirror directory {mirror_directory:?}",
+            names.len()
         );
+        let source = DirectorySource::new(mirror_directory);
+        index.head = source.layout.head();
+        let source: Arc<dyn MirrorSource> = Arc::new(source);
+        let symbols = index.has_symbols();
+        let crates = names
+            .par_iter()
+            .map(|name| (name, Index::load_crate(&source, name, symbols)))
+            .collect::<Vec<_>>();
+
+        // Crate names may differ in case from their index file names
+        let keys = index
+            .crates
+            .keys()
+            .map(|x| (x.to_lowercase(), x.clone()))
+            .collect::<HashMap<_, _>>();
+        for (name, crate_) in crates {
+            if let Some(key) = keys.get(name) {
+                index.crates.remove(key);
+            }
+            match crate_ {
+                Ok(Some(crate_)) => {
+                    index.crates.insert(crate_.name.clone(), crate_);
+                }
+                Ok(None) => {}
+                Err(Error::ReadIndexFile { source, .. })
+                    if source.kind() == io::ErrorKind::NotFound =>
+                {
+                    debug!("Removed {name:?}");
+                }
+                Err(e) => warn!("{e}"),
+            }
+        }
 
-        let cache_file = mirror_directory.join("search.json");
-        index.save(&cache_file)?;
+        for crate_ in index.crates.values_mut() {
+            crate_.source = Some(source.clone());
+        }
+        index.save_with_snapshot(cache_directory)?;
 
         Ok(index)
     }
 
+    /**
+    Parse every index file and crate file of a mirror source, without a cache file, optionally also
+    extracting public items like [`Index::load_from_mirror_directory_with_symbols`]
+
+    Crates keep a reference to the source to read their details on demand.
+    */
+    pub fn from_source(source: Arc<dyn MirrorSource>, symbols: bool) -> Result<Index> {
+        Index::from_source_with_progress(source, symbols, &|_| {})
+    }
+
+    /// Like [`Index::from_source`], but report the progress of each phase to a callback
+    pub fn from_source_with_progress(
+        source: Arc<dyn MirrorSource>,
+        symbols: bool,
+        progress: &(dyn Fn(Progress) + Sync),
+    ) -> Result<Index> {
+        let index_files = Counter::start(Phase::IndexFiles, None, progress);
+        let names = source
+            .index_files_with_progress(&|| index_files.inc())
+            .map_err(Error::ListIndexFiles)?;
+        progress(Progress {
+            phase: Phase::IndexFiles,
+            done: names.len(),
+            total: Some(names.len()),
+        });
+
+        let parse = Counter::start(Phase::Parse, Some(names.len()), progress);
+        let mut crates = names
+            .into_par_iter()
+            .filter_map(|name| {
+                let r = Index::read_crate(&source, &name);
+                parse.inc();
+                match r {
+                    Ok(crate_) => crate_,
+                    Err(e) => {
+                        // Cargo's `.cache` files may be empty for crates that don't exist
+                        warn!("{e}");
+                        None
+                    }
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let crate_files = Counter::start(Phase::CrateFiles, Some(crates.len()), progress);
+        crates.par_iter_mut().for_each(|crate_| {
+            Index::read_crate_files(&source, crate_, symbols);
+            crate_files.inc();
+        });
+
+        Ok(Index {
+            crates: crates.into_iter().map(|x| (x.name.clone(), x)).collect(),
+            head: None,
+        })
+    }
+
+    /**
+    Read a crate's index file and its description (and public items) from its crate file
+
+    `None` if the source doesn't have any of the crate's crate files, e.g. cargo hasn't downloaded
+    it.
+    */
+    fn load_crate(
+        source: &Arc<dyn MirrorSource>,
+        name: &str,
+        symbols: bool,
+    ) -> Result<Option<Crate>> {
+        let mut crate_ = Index::read_crate(source, name)?;
+        if let Some(crate_) = &mut crate_ {
+            Index::read_crate_files(source, crate_, symbols);
+        }
+        Ok(crate_)
+    }
+
+    /// Read a crate's index file, or `None` if the source doesn't have any of its crate files
+    fn read_crate(source: &Arc<dyn MirrorSource>, name: &str) -> Result<Option<Crate>> {
+        let index_file = source.index_file_path(name);
+        let data = source
+            .read_index_file(name)
+            .map_err(|e| Error::ReadIndexFile {
+                path: index_file.clone(),
+                source: e,
+            })?;
+        let crate_ = Crate::from_index_file(&index_file, &data)?;
+
+        if source
+            .crate_versions(&crate_.name)
+            .is_some_and(|x| x.is_empty())
+        {
+            return Ok(None);
+        }
+
+        trace!("{crate_:?}");
+        Ok(Some(crate_))
+    }
+
+    /// Read a crate's description (and public items) from its crate file and the sizes of all its
+    /// crate files
+    fn read_crate_files(source: &Arc<dyn MirrorSource>, crate_: &mut Crate, symbols: bool) {
+        crate_.add_description(source.as_ref());
+        crate_.add_crate_file_sizes(source.as_ref());
+        if symbols {
+            crate_.add_symbols(source.as_ref());
+        }
+        crate_.source = Some(source.clone());
+    }
+
+    /**
+    Load the snapshot of the cache file kept by the previous rebuild (`search.prev.json`) from the
+    directory containing the cache file (the mirror directory unless a separate cache directory is
+    used)
+
+    See [`Index::diff`].
+    */
+    pub fn load_snapshot(cache_directory: &Path) -> Result<Index> {
+        Index::read_cache_file(&cache_directory.join("search.prev.json"))
+    }
+
+    /// Load any cache file, e.g. a copy kept from an earlier sync, without checking its freshness
+    pub fn read_cache_file(cache_file: &Path) -> Result<Index> {
+        let s = read_to_string(cache_file).map_err(|source| Error::ReadCacheFile {
+            path: cache_file.to_path_buf(),
+            source,
+        })?;
+        Index::from_json(&s).map_err(|source| Error::ParseCacheFile {
+            path: cache_file.to_path_buf(),
+            source,
+        })
+    }
+
+    /**
+    Totals and distributions of this index's crates, see [`Stats`]; `top` limits the licenses,
+    categories, and keywords
+
+    Only reads the cache file's fields, see [`Stats`].
+    */
+    pub fn stats(&self, top: usize) -> Stats {
+        Stats::new(self.crates.values(), top)
+    }
+
+    /// Crates grouped by why their descriptions are missing or which quirk was worked around
+    pub fn description_report(&self) -> DescriptionReport {
+        DescriptionReport::new(self.crates.values())
+    }
+
+    /// Changes from a previous index to this one
+    pub fn diff(&self, previous: &Index) -> Diff {
+        Diff::new(&previous.crates, &self.crates)
+    }
+
+    /// Save the cache file to the cache directory, keeping the previous one as a snapshot to diff
+    /// against
+    fn save_with_snapshot(&self, cache_directory: &Path) -> Result<()> {
+        create_dir_all(cache_directory).map_err(Error::io(cache_directory))?;
+        let cache_file = cache_directory.join("search.json");
+        if cache_file.is_file() {
+            let snapshot_file = cache_directory.join("search.prev.json");
+            info!("Keep previous cache file as {snapshot_file:?}");
+            rename(&cache_file, &snapshot_file).map_err(Error::io(&snapshot_file))?;
+        }
+        self.save(&cache_file)?;
+
+        let marker = cache_directory.join(SYMBOLS_MARKER_FILE);
+        if self.has_symbols() {
+            File::create(&marker).map_err(Error::io(&marker))?;
+        } else if marker.is_file() {
+            remove_file(&marker).map_err(Error::io(&marker))?;
+        }
+        Ok(())
+    }
+
     fn save(&self, cache_file: &Path) -> Result<()> {
         info!("Save cache file {cache_file:?}");
-        Ok(BufWriter::new(File::create(cache_file)?).write_all(self.to_json()?.as_bytes())?)
+        let json = self.to_json()?;
+        File::create(cache_file)
+            .and_then(|f| BufWriter::new(f).write_all(json.as_bytes()))
+            .map_err(Error::io(cache_file))
+    }
+
+    /**
+    Merge indexes from multiple registries into one
+
+    Each crate is tagged with the name of the registry it came from.
+    When the same crate name exists in more than one registry, the crate from the first registry is
+    kept and the others are attached to it as its `conflicts` so they can be shown explicitly.
+    */
+    pub fn merge(indexes: Vec<(String, Index)>) -> Index {
+        let mut crates = BTreeMap::<String, Crate>::new();
+
+        for (registry, index) in indexes {
+            for (name, mut crate_) in index.crates {
+                crate_.registry = Some(registry.clone());
+                if let Some(existing) = crates.get_mut(&name) {
+                    debug!(
+                        "Crate {name:?} is in both {:?} and {registry:?}",
+                        existing.registry.as_ref().unwrap(),
+                    );
+                    existing.conflicts.push(crate_);
+                } else {
+                    crates.insert(name, crate_);
+                }
+            }
+        }
+
+        Index { crates, head: None }
+    }
+
+    /**
+    Git HEAD commit of the index directory the crates were read from
+
+    Saved in the cache file, which is stale once the mirror's HEAD differs. `None` for sparse and
+    cargo indexes and indexes not read from a mirror directory.
+    */
+    pub fn head(&self) -> Option<&str> {
+        self.head.as_deref()
+    }
+
+    /// Get a crate by its exact name
+    pub fn get(&self, name: &str) -> Option<&Crate> {
+        self.crates.get(name)
+    }
+
+    /// Whether a crate with this exact name is in the index
+    pub fn contains(&self, name: &str) -> bool {
+        self.crates.contains_key(name)
+    }
+
+    /// Number of crates
+    pub fn len(&self) -> usize {
+        self.crates.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.crates.is_empty()
     }
 
-    pub fn search(&self, queries: &[String], case_insensitive: bool) -> Search {
-        Search::new(queries, case_insensitive, &self.0)
+    /// All crates, in name order
+    pub fn iter(&self) -> impl Iterator<Item = &Crate> {
+        self.crates.values()
+    }
+
+    /// All crate names, in order
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.crates.keys().map(|x| x.as_str())
     }
 
     /**
-    Custom JSON serializer enabling one entry per line
+    New index with only the crates that match a predicate, e.g. to search a subset
+
+    ```
+    # use {panamax_search_lib::{Index, MemorySource}, std::sync::Arc};
+    # let mut source = MemorySource::default();
+    # source.add_index_file("foo", r#"{"name":"foo","vers":"0.1.0","deps":[],"features":{},"yanked":false}"#);
+    # let index = Index::from_source(Arc::new(source), false).unwrap();
+    let described = index.filter(|x| x.description().is_some());
+    assert!(described.is_empty());
+    ```
+    */
+    pub fn filter(&self, predicate: impl Fn(&Crate) -> bool) -> Index {
+        Index {
+            crates: self
+                .crates
+                .iter()
+                .filter(|(_name, crate_)| predicate(crate_))
+                .map(|(name, crate_)| (name.clone(), crate_.clone()))
+                .collect(),
+            head: self.head.clone(),
+        }
+    }
+
+    /// Crates whose names start with a prefix, in name order
+    pub fn prefix<'a>(&'a self, prefix: &'a str) -> impl Iterator<Item = &'a Crate> + 'a {
+        self.crates
+            .range(prefix.to_string()..)
+            .take_while(move |(name, _crate_)| name.starts_with(prefix))
+            .map(|(_name, crate_)| crate_)
+    }
+
+    /// Search like `cargo search`; fails if a query is not a valid regular expression
+    pub fn search(&self, queries: &[String], case_insensitive: bool) -> Result<Search> {
+        Search::new(queries, case_insensitive, &self.crates)
+    }
+
+    /// Configure a search with [`SearchBuilder`]
+    pub fn search_builder(&self, queries: &[String]) -> SearchBuilder<'_> {
+        SearchBuilder::new(queries, &self.crates)
+    }
+
+    /// Search with options; fails if a query is not a valid regular expression
+    pub fn search_with(
+        &self,
+        queries: &[String],
+        options: SearchOptions,
+    ) -> Result<SearchResults<'_>> {
+        self.search_builder(queries).options(options).build()
+    }
+
+    /// Search public items by name; fails if a query is not a valid regular expression
+    pub fn search_symbols(
+        &self,
+        queries: &[String],
+        case_insensitive: bool,
+    ) -> Result<SymbolSearch<'_>> {
+        SymbolSearch::new(queries, case_insensitive, &self.crates)
+    }
+
+    /// True if the index was built with [`Index::load_from_mirror_directory_with_symbols`]
+    pub fn has_symbols(&self) -> bool {
+        self.crates
+            .values()
+            .flat_map(|x| std::iter::once(x).chain(&x.conflicts))
+            .any(|x| !x.symbols.is_empty())
+    }
+
+    /**
+    Custom JSON serializer enabling one entry per line, preceded by the git HEAD commit if known
 
     ```text
-    {"name-a":{"d":"Description","v":"0.0.0","y":"0.0.0"},
+    {"$head":"0123456789abcdef0123456789abcdef01234567",
+    "name-a":{"d":"Description","v":"0.0.0","y":"0.0.0"},
     "name-b":{"d":"Description","v":"0.0.0","y":"0.0.0"},
     "name-z":{"d":"Description","v":"0.0.0","y":"0.0.0"}}
     ```
     */
     fn to_json(&self) -> Result<String> {
-        let r = self
-            .0
+        let crates = self
+            .crates
             .par_iter()
-            .map(|(name, value)| (name, serde_json::to_string(value)))
-            .collect::<Vec<_>>();
+            .map(|(name, value)| Ok(format!("\"{name}\":{}", serde_json::to_string(value)?)))
+            .collect::<serde_json::Result<Vec<_>>>()
+            .map_err(Error::Serialize)?;
 
-        let errors = r
-            .par_iter()
-            .filter(|(_name, value)| value.is_err())
-            .collect::<Vec<_>>();
+        Ok(format!(
+            "{{{}}}",
+            self.head
+                .iter()
+                .map(|x| format!("\"{HEAD_KEY}\":\"{x}\""))
+                .chain(crates)
+                .collect::<Vec<_>>()
+                .join(",\n")
+        ))
+    }
 
-        if errors.is_empty() {
-            Ok(format!(
-                "{{{}}}",
-                r.par_iter()
-                    .map(|(name, value)| format!("\"{name}\":{}", value.as_ref().unwrap()))
-                    .collect::<Vec<_>>()
-                    .join(",\n")
-            ))
-        } else {
-            Err(anyhow!("Serialization to JSON failed: {errors:?}"))
-        }
+    fn from_json(s: &str) -> serde_json::Result<Index> {
+        serde_json::from_str::<Index>(s)
     }
+}
 
-    fn from_json(s: &str) -> Result<Index> {
-        Ok(serde_json::from_str::<Index>(s)?)
+impl<'a> IntoIterator for &'a Index {
+    type Item = &'a Crate;
+    type IntoIter = std::collections::btree_map::Values<'a, String, Crate>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.crates.values()
     }
 }
 
@@ -161,12 +777,22 @@ impl<'de> Visitor<'de> for IndexVisitor {
         // Custom deserializer that sets each crate's name from the key
 
         let mut crates = BTreeMap::new();
+        let mut head = None;
 
-        while let Some((name, mut crate_)) = access.next_entry::<String, Crate>()? {
-            crate_.name = name.clone();
-            crates.insert(name, crate_);
+        while let Some(name) = access.next_key::<String>()? {
+            if name == HEAD_KEY {
+                head = Some(access.next_value::<String>()?);
+            } else {
+                let mut crate_ = access.next_value::<Crate>()?;
+                crate_.name = name.clone();
+                // Older cache files don't record why a description is missing
+                if crate_.description.is_none() && crate_.description_status.is_ok() {
+                    crate_.description_status = DescriptionStatus::Unknown;
+                }
+                crates.insert(name, crate_);
+            }
         }
 
-        Ok(Index(crates))
+        Ok(Index { crates, head })
     }
 }
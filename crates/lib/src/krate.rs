@@ -1,15 +1,23 @@
 use {
-    crate::functions::path_parent,
+    crate::{
+        description::DescriptionStatus,
+        details::{Details, IndexEntry},
+        error::Error,
+        functions::index_entries,
+        source::MirrorSource,
+        stats::Manifest,
+        symbol::{extract_symbols, module_path, Symbol},
+    },
     anyhow::{anyhow, Result},
     flate2::read::GzDecoder,
     log::*,
-    rev_lines::RevLines,
     semver::Version,
     serde::{Deserialize, Serialize},
     std::{
-        fs::File,
+        fs::read,
         io::Read,
         path::{Path, PathBuf},
+        sync::Arc,
     },
 };
 
@@ -21,181 +29,579 @@ pub struct Crate {
     #[serde(rename = "d", skip_serializing_if = "Option::is_none")]
     pub description: Option<String>,
 
+    /// Why the description is missing, or which quirk was worked around to read it
+    #[serde(
+        rename = "e",
+        default,
+        skip_serializing_if = "DescriptionStatus::is_ok"
+    )]
+    pub description_status: DescriptionStatus,
+
     #[serde(rename = "v", skip_serializing_if = "Option::is_none")]
     pub latest_ny: Option<Version>,
 
     #[serde(rename = "y", skip_serializing_if = "Option::is_none")]
     pub latest: Option<Version>,
+
+    /// Number of versions including yanked versions (zero if loaded from an older cache file)
+    #[serde(rename = "n", default, skip_serializing_if = "is_zero")]
+    pub versions: usize,
+
+    /// Number of yanked versions (zero if loaded from an older cache file)
+    #[serde(rename = "x", default, skip_serializing_if = "is_zero")]
+    pub yanked: usize,
+
+    #[serde(rename = "s", default, skip_serializing_if = "Vec::is_empty")]
+    pub symbols: Vec<Symbol>,
+
+    /// Fields of the `Cargo.toml` the description was read from, for [`crate::Stats`]
+    #[serde(rename = "m", default, skip_serializing_if = "Option::is_none")]
+    pub(crate) manifest: Option<Manifest>,
+
+    /// Number of crate files in the mirror (zero if loaded from an older cache file)
+    #[serde(rename = "f", default, skip_serializing_if = "is_zero")]
+    pub crate_files: usize,
+
+    /// Total size of the crate files in bytes (zero if loaded from an older cache file)
+    #[serde(rename = "z", default, skip_serializing_if = "is_zero_u64")]
+    pub crate_file_size: u64,
+
+    /// Registry the crate came from; only set by [`crate::Index::merge`]
+    #[serde(skip)]
+    pub registry: Option<String>,
+
+    /// Crates with the same name in other registries; only set by [`crate::Index::merge`]
+    #[serde(skip)]
+    pub conflicts: Vec<Crate>,
+
+    /// All versions in the index file, kept from parsing it until [`Crate::add_crate_file_sizes`]
+    #[serde(skip)]
+    all_versions: Vec<Version>,
+
+    /// Mirror the crate came from, used to read its details on demand
+    #[serde(skip)]
+    pub(crate) source: Option<Arc<dyn MirrorSource>>,
+}
+
+fn is_zero(n: &usize) -> bool {
+    *n == 0
+}
+
+fn is_zero_u64(n: &u64) -> bool {
+    *n == 0
 }
 
 impl Crate {
-    pub fn new(index_file: &Path) -> Result<Crate> {
+    pub fn new(index_file: &Path) -> Result<Crate, Error> {
         debug!("{index_file:?}");
 
+        let data = read(index_file).map_err(|source| Error::ReadIndexFile {
+            path: index_file.to_path_buf(),
+            source,
+        })?;
+        Crate::from_index_file(index_file, &data)
+    }
+
+    /// Parse the contents of an index file; the path is only used in errors
+    pub(crate) fn from_index_file(index_file: &Path, data: &[u8]) -> Result<Crate, Error> {
+        // Index files are JSON lines, but cargo's `.cache` files have a binary format
+        let lines = index_entries(index_file, data)?;
+        let count = lines.len();
+
         let mut name = None;
         let mut latest_ny = None;
         let mut latest = None;
+        let mut all_versions = vec![];
+        let mut yanked = 0;
 
-        match File::open(index_file) {
-            Ok(f) => {
-                // Parse lines of the crate's index file from the bottom up
-                for line in RevLines::new(f).flatten() {
-                    // Deserialize line as `CrateIndex`
-                    match serde_json::from_str::<CrateIndex>(&line) {
-                        Ok(i) => {
-                            // Set name once
-                            if name.is_none() {
-                                name = Some(i.name.clone());
-                            }
-
-                            if i.yanked {
-                                if latest.is_none() {
-                                    latest = Some(i.vers.clone());
-                                }
-                            } else {
-                                if latest_ny.is_none() {
-                                    latest_ny = Some(i.vers.clone());
-                                }
-                                break;
-                            }
-                        }
-                        Err(e) => {
-                            return Err(anyhow!(
-                                "{index_file:?}: Deserialization errror: {e}; line = {line:?}"
-                            ));
-                        }
+        // Parse lines of the crate's index file from the bottom up, fully until the latest
+        // non-yanked version and then just for the counts
+        for (i, line) in lines.iter().rev().enumerate() {
+            if latest_ny.is_some() {
+                match serde_json::from_str::<CrateVersion>(line) {
+                    Ok(x) => {
+                        all_versions.push(x.vers);
+                        yanked += usize::from(x.yanked);
                     }
+                    Err(e) => debug!("{index_file:?}:{}: Skip invalid line: {e}", count - i),
                 }
+                continue;
+            }
 
-                if name.is_none() {
-                    return Err(anyhow!("{index_file:?}: No name"));
-                }
+            // Deserialize line as `CrateIndex`
+            match serde_json::from_str::<CrateIndex>(line) {
+                Ok(i) => {
+                    all_versions.push(i.vers.clone());
 
-                if latest.is_none() && latest_ny.is_none() {
-                    return Err(anyhow!(
-                        "{index_file:?}: No latest or latest non-yanked version"
-                    ));
-                }
+                    // Set name once
+                    if name.is_none() {
+                        name = Some(i.name.clone());
+                    }
 
-                Ok(Crate {
-                    name: name.unwrap(),
-                    description: None,
-                    latest_ny,
-                    latest,
-                })
+                    if i.yanked {
+                        yanked += 1;
+                        if latest.is_none() {
+                            latest = Some(i.vers.clone());
+                        }
+                    } else {
+                        latest_ny = Some(i.vers.clone());
+                    }
+                }
+                Err(source) => {
+                    return Err(Error::InvalidIndexLine {
+                        path: index_file.to_path_buf(),
+                        line: count - i,
+                        source,
+                    });
+                }
             }
-            Err(e) => Err(anyhow!("{index_file:?}: Could not open file: {e}")),
         }
+
+        let Some(name) = name else {
+            return Err(Error::NoVersions(index_file.to_path_buf()));
+        };
+
+        Ok(Crate {
+            name,
+            description: None,
+            description_status: DescriptionStatus::Unknown,
+            latest_ny,
+            latest,
+            versions: all_versions.len(),
+            yanked,
+            symbols: vec![],
+            manifest: None,
+            crate_files: 0,
+            crate_file_size: 0,
+            registry: None,
+            conflicts: vec![],
+            all_versions,
+            source: None,
+        })
     }
 
-    pub fn add_description(&mut self, index_file: &Path) {
-        let (crate_file, version) = self.crate_file_and_version(index_file);
+    pub fn name(&self) -> &str {
+        &self.name
+    }
 
-        match self.get_cargo_toml(&crate_file, &version) {
-            Ok(content) => {
-                // Try to deserialize with a `package` section
-                match toml::from_str::<CargoTomlPackage>(&content) {
-                    Ok(t) => {
-                        if let Some(d) = t.package.description {
-                            self.description = Some(d.clone());
-                        } else {
-                            debug!("{crate_file:?}: No package.description");
-                        }
+    /// Description from the latest version's `Cargo.toml`, if it has one
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+
+    /// Why the description is missing, or which quirk was worked around to read it
+    pub fn description_status(&self) -> DescriptionStatus {
+        self.description_status
+    }
+
+    /// Latest non-yanked version, if any
+    pub fn latest_non_yanked(&self) -> Option<&Version> {
+        self.latest_ny.as_ref()
+    }
+
+    /// Latest version if it was yanked after the latest non-yanked version
+    pub fn latest_yanked(&self) -> Option<&Version> {
+        self.latest.as_ref()
+    }
+
+    /// Number of yanked versions
+    pub fn yanked_versions(&self) -> usize {
+        self.yanked
+    }
+
+    /// Public items, if the index was built with symbols
+    pub fn symbols(&self) -> &[Symbol] {
+        &self.symbols
+    }
+
+    /// Registry the crate came from; only set by [`crate::Index::merge`]
+    pub fn registry(&self) -> Option<&str> {
+        self.registry.as_deref()
+    }
+
+    /// Crates with the same name in other registries
+    pub fn conflicts(&self) -> &[Crate] {
+        &self.conflicts
+    }
+
+    /**
+    Version to display
+
+    The latest version if `include_yanked` and the crate has yanked versions, otherwise the latest
+    non-yanked version or "0.0.0" if all versions were yanked; this matches `cargo search` behavior.
+    */
+    pub fn version(&self, include_yanked: bool) -> Version {
+        match (&self.latest, &self.latest_ny) {
+            (Some(latest), _) if include_yanked => latest.clone(),
+            (_, Some(latest_ny)) => latest_ny.clone(),
+            _ => Version::new(0, 0, 0),
+        }
+    }
+
+    /// Latest version whether yanked or not
+    pub fn newest(&self) -> Option<&Version> {
+        self.latest.as_ref().or(self.latest_ny.as_ref())
+    }
+
+    /**
+    This crate followed by its conflicts, each with its registry tag
+
+    ```text
+    [crates-io; conflicts with internal]
+    [internal; conflicts with crates-io]
+    ```
+
+    Tags are `None` unless the crate came from an index built with [`crate::Index::merge`].
+    */
+    pub fn tagged(&self) -> Vec<(&Crate, Option<String>)> {
+        let group = std::iter::once(self)
+            .chain(self.conflicts.iter())
+            .collect::<Vec<_>>();
+
+        group
+            .iter()
+            .map(|crate_| {
+                let tag = crate_.registry.as_ref().map(|registry| {
+                    let others = group
+                        .iter()
+                        .filter(|x| !std::ptr::eq(**x, *crate_))
+                        .filter_map(|x| x.registry.as_deref())
+                        .collect::<Vec<_>>();
+                    if others.is_empty() {
+                        format!("[{registry}]")
+                    } else {
+                        format!("[{registry}; conflicts with {}]", others.join(", "))
                     }
-                    Err(_e) => {
-                        // Try to deserialize with a `project` section
-                        match toml::from_str::<CargoTomlProject>(&content) {
-                            Ok(t) => {
-                                debug!("{crate_file:?}: Has project section");
-                                if let Some(d) = t.project.description {
-                                    self.description = Some(d.clone());
-                                } else {
-                                    debug!("{crate_file:?}: No project.description");
-                                }
-                            }
-                            Err(e) => {
-                                // Failed to deserialize
-                                debug!("{crate_file:?}: Deserialization error: {e:?}");
-                            }
+                });
+                (*crate_, tag)
+            })
+            .collect()
+    }
+
+    /// Read the description, and the `Cargo.toml` fields counted by [`crate::Stats`], from the
+    /// crate file
+    pub fn add_description(&mut self, source: &dyn MirrorSource) {
+        let version = self.available_version(source);
+        let crate_file = self.crate_file_name(&version);
+
+        let (description, status) = match self.find_cargo_toml(source, &version) {
+            Ok((content, lowercase)) => {
+                self.manifest = Manifest::parse(&content);
+
+                // Try to deserialize with a `package` section, then a `project` section
+                let (package, status) = match toml::from_str::<CargoTomlPackage>(&content) {
+                    Ok(t) => (Some(t.package), DescriptionStatus::Ok),
+                    Err(_e) => match toml::from_str::<CargoTomlProject>(&content) {
+                        Ok(t) => {
+                            debug!("{crate_file:?}: Has project section");
+                            (Some(t.project), DescriptionStatus::ProjectSection)
                         }
+                        Err(e) => {
+                            debug!("{crate_file:?}: Deserialization error: {e:?}");
+                            (None, DescriptionStatus::MalformedManifest)
+                        }
+                    },
+                };
+                match package.map(|x| x.description) {
+                    Some(Some(d)) if lowercase => (Some(d), DescriptionStatus::LowercaseManifest),
+                    Some(Some(d)) => (Some(d), status),
+                    Some(None) => {
+                        debug!("{crate_file:?}: No description");
+                        (None, DescriptionStatus::NoDescription)
                     }
+                    None => (None, status),
                 }
             }
+            Err(status) => {
+                debug!("{crate_file:?}: {status}");
+                (None, status)
+            }
+        };
+
+        self.description = description;
+        self.description_status = status;
+    }
+
+    /// Count the crate files of all versions parsed from the index file and their total size
+    pub fn add_crate_file_sizes(&mut self, source: &dyn MirrorSource) {
+        for version in std::mem::take(&mut self.all_versions) {
+            if let Ok(size) = source.crate_file_size(&self.name, &version) {
+                self.crate_files += 1;
+                self.crate_file_size += size;
+            }
+        }
+    }
+
+    pub fn add_symbols(&mut self, source: &dyn MirrorSource) {
+        let version = self.available_version(source);
+        let crate_file = self.crate_file_name(&version);
+
+        match self.get_symbols(source, &version) {
+            Ok(symbols) => {
+                self.symbols = symbols;
+            }
             Err(e) => {
                 debug!("{crate_file:?}: {e}");
             }
         }
     }
 
-    fn crate_file_and_version(&self, index_file: &Path) -> (PathBuf, String) {
-        // Use the latest non-yanked version if possible, otherwise use the latest yanked version
-        let version = if let Some(latest_ny) = &self.latest_ny {
-            latest_ny.to_string()
+    /// Use the latest non-yanked version if possible, otherwise use the latest yanked version
+    fn crate_version(&self) -> Version {
+        if let Some(latest_ny) = &self.latest_ny {
+            latest_ny.clone()
         } else if let Some(latest) = &self.latest {
-            latest.to_string()
+            latest.clone()
         } else {
             unreachable!()
+        }
+    }
+
+    /// Version to read the crate file of, falling back to the latest available if the source
+    /// only has some crate files, e.g. cargo's cache
+    fn available_version(&self, source: &dyn MirrorSource) -> Version {
+        let version = self.crate_version();
+        match source.crate_versions(&self.name) {
+            Some(available) if !available.contains(&version) => {
+                available.last().cloned().unwrap_or(version)
+            }
+            _ => version,
+        }
+    }
+
+    /// Crate file name for log messages
+    fn crate_file_name(&self, version: &Version) -> String {
+        format!("{}-{version}.crate", self.name)
+    }
+
+    fn open_crate_file<'a>(
+        &self,
+        source: &'a dyn MirrorSource,
+        version: &Version,
+    ) -> Result<tar::Archive<GzDecoder<Box<dyn Read + 'a>>>> {
+        let file = source.open_crate_file(&self.name, version)?;
+        Ok(tar::Archive::new(GzDecoder::new(file)))
+    }
+
+    /**
+    Read the crate's details from its index file and crate file
+
+    Fails if the crate didn't come from a mirror via [`crate::Index`] or its index file can't be
+    read; missing or unreadable crate files just leave the license and README empty.
+    */
+    pub fn details(&self) -> Result<Details, Error> {
+        let Some(source) = &self.source else {
+            return Err(Error::UnknownMirror(self.name.clone()));
+        };
+
+        self.read_details(source.as_ref(), self.available_version(source.as_ref()))
+    }
+
+    /// Like [`Crate::details`], but for a specific version
+    pub fn version_details(&self, version: &Version) -> Result<Details, Error> {
+        let Some(source) = &self.source else {
+            return Err(Error::UnknownMirror(self.name.clone()));
+        };
+
+        self.read_details(source.as_ref(), version.clone())
+    }
+
+    fn read_details(&self, source: &dyn MirrorSource, version: Version) -> Result<Details, Error> {
+        let index_file = source.index_file_path(&self.name);
+        let data = source
+            .read_index_file(&self.name)
+            .map_err(|source| Error::ReadIndexFile {
+                path: index_file.clone(),
+                source,
+            })?;
+        let entries = index_entries(&index_file, &data)?
+            .iter()
+            .enumerate()
+            .map(|(i, line)| {
+                serde_json::from_str::<IndexEntry>(line).map_err(|source| Error::InvalidIndexLine {
+                    path: index_file.clone(),
+                    line: i + 1,
+                    source,
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let entry = entries
+            .iter()
+            .rev()
+            .find(|x| x.vers == version)
+            .ok_or_else(|| Error::VersionNotFound {
+                name: self.name.clone(),
+                version: version.to_string(),
+            })?;
+
+        let (license, readme) = match self.get_license_and_readme(source, &version) {
+            Ok(r) => r,
+            Err(e) => {
+                debug!("{:?}: {e}", self.crate_file_name(&version));
+                (None, None)
+            }
         };
 
-        // Convert the index file path into the related crate file path
-        let crate_file = match self.name.len() {
-            // `mirror/crates/1/a/0.0.0/a-0.0.0.crate`
-            1 => path_parent(index_file, 3)
-                .join("crates")
-                .join("1")
-                .join(&self.name[..1]),
-
-            // `mirror/crates/2/aa/0.0.0/aa-0.0.0.crate`
-            2 => path_parent(index_file, 3)
-                .join("crates")
-                .join("2")
-                .join(&self.name[..2]),
-
-            // `mirror/crates/3/a/aaa/0.0.0/a-0.0.0.crate`
-            3 => path_parent(index_file, 4)
-                .join("crates")
-                .join("3")
-                .join(&self.name[..1]),
-
-            // `mirror/crates/aa/aa/0.0.0/aaaa-0.0.0.crate`
-            _ => path_parent(index_file, 4)
-                .join("crates")
-                .join(&self.name[..2])
-                .join(&self.name[2..4]),
+        Ok(Details {
+            versions: entries.iter().map(|x| (x.vers.clone(), x.yanked)).collect(),
+            license,
+            rust_version: entry.rust_version.clone(),
+            dependencies: entry.deps.clone(),
+            features: entry.all_features(),
+            readme,
+            version,
+        })
+    }
+
+    fn get_license_and_readme(
+        &self,
+        source: &dyn MirrorSource,
+        version: &Version,
+    ) -> Result<(Option<String>, Option<String>)> {
+        let cargo_toml = self.get_cargo_toml(source, version)?;
+        let package = toml::from_str::<CargoTomlPackage>(&cargo_toml)
+            .map(|x| x.package)
+            .or_else(|_| toml::from_str::<CargoTomlProject>(&cargo_toml).map(|x| x.project))?;
+
+        let license = match (package.license, package.license_file) {
+            (Some(toml::Value::String(license)), _) => Some(license),
+            (_, Some(toml::Value::String(file))) => Some(format!("See {file}")),
+            _ => None,
+        };
+
+        // Use the `readme` field if it's a path, otherwise look for a top-level README file
+        let readme_path = match package.readme {
+            Some(toml::Value::String(path)) => Some(path),
+            Some(toml::Value::Boolean(false)) => return Ok((license, None)),
+            _ => None,
+        };
+
+        let prefix = PathBuf::from(format!("{}-{version}", self.name));
+        let mut r = self.open_crate_file(source, version)?;
+
+        for entry in r.entries()? {
+            let Ok(mut entry) = entry else {
+                continue;
+            };
+            let path = entry.path()?.to_path_buf();
+            let Ok(path) = path.strip_prefix(&prefix) else {
+                continue;
+            };
+
+            let is_readme = match &readme_path {
+                Some(readme_path) => path == Path::new(readme_path.trim_start_matches("./")),
+                None => {
+                    path.components().count() == 1
+                        && path
+                            .to_str()
+                            .is_some_and(|x| x.to_lowercase().starts_with("readme"))
+                }
+            };
+
+            if is_readme {
+                let mut s = String::new();
+                entry.read_to_string(&mut s)?;
+                return Ok((license, Some(s)));
+            }
+        }
+
+        Ok((license, None))
+    }
+
+    fn get_cargo_toml(&self, source: &dyn MirrorSource, version: &Version) -> Result<String> {
+        self.find_cargo_toml(source, version)
+            .map(|(content, _lowercase)| content)
+            .map_err(|status| anyhow!("{status}"))
+    }
+
+    /// Contents of the crate file's `Cargo.toml` and whether it's named `cargo.toml`, or why it
+    /// couldn't be read
+    fn find_cargo_toml(
+        &self,
+        source: &dyn MirrorSource,
+        version: &Version,
+    ) -> Result<(String, bool), DescriptionStatus> {
+        let mut r = self
+            .open_crate_file(source, version)
+            .map_err(|_| DescriptionStatus::MissingCrateFile)?;
+        let entries = r
+            .entries()
+            .map_err(|_| DescriptionStatus::CorruptCrateFile)?;
+
+        let mut count = 0;
+        for entry in entries {
+            // Reading a corrupt crate file fails at its first entry
+            let mut entry = entry.map_err(|_| DescriptionStatus::CorruptCrateFile)?;
+            count += 1;
+
+            let Ok(path) = entry.path() else {
+                continue;
+            };
+            let path = path.to_string_lossy().to_string();
+            for filename in ["Cargo.toml", "cargo.toml"] {
+                if path == format!("{}-{version}/{filename}", self.name) {
+                    let lowercase = filename == "cargo.toml";
+                    if lowercase {
+                        debug!("{:?}: Has cargo.toml", self.crate_file_name(version));
+                    }
+                    let mut s = String::new();
+                    entry
+                        .read_to_string(&mut s)
+                        .map_err(|_| DescriptionStatus::CorruptCrateFile)?;
+                    return Ok((s, lowercase));
+                }
+            }
         }
-        .join(&self.name)
-        .join(&version)
-        .join(format!("{}-{version}.crate", self.name));
 
-        (crate_file, version)
+        Err(if count == 0 {
+            DescriptionStatus::EmptyCrateFile
+        } else {
+            DescriptionStatus::NoManifest
+        })
     }
 
-    fn get_cargo_toml(&self, crate_file: &Path, version: &str) -> Result<String> {
-        let file = File::open(crate_file)?;
-        let decoder = GzDecoder::new(file);
-        let mut r = tar::Archive::new(decoder);
+    fn get_symbols(&self, source: &dyn MirrorSource, version: &Version) -> Result<Vec<Symbol>> {
+        let crate_file = self.crate_file_name(version);
+        let mut r = self.open_crate_file(source, version)?;
+
+        let crate_ident = self.name.replace('-', "_");
+        let src_dir = PathBuf::from(format!("{}-{version}", self.name)).join("src");
 
+        let mut symbols = vec![];
         for entry in r.entries()? {
             match entry {
                 Ok(mut entry) => {
-                    let path = entry.path()?;
-                    let path = path.to_str().unwrap();
-                    for filename in ["Cargo.toml", "cargo.toml"] {
-                        if path == format!("{}-{version}/{filename}", self.name) {
-                            if filename == "cargo.toml" {
-                                debug!("{crate_file:?}: Has cargo.toml");
-                            }
-                            let mut s = String::new();
-                            entry.read_to_string(&mut s)?;
-                            return Ok(s);
-                        }
+                    let path = entry.path()?.to_path_buf();
+                    if path.extension().and_then(|x| x.to_str()) != Some("rs") {
+                        continue;
+                    }
+                    let Some(module) = path.strip_prefix(&src_dir).ok().and_then(module_path)
+                    else {
+                        continue;
+                    };
+
+                    let mut prefix = vec![crate_ident.clone()];
+                    prefix.extend(module);
+
+                    let mut s = String::new();
+                    if let Err(e) = entry.read_to_string(&mut s) {
+                        debug!("{crate_file:?}: {path:?}: {e}");
+                        continue;
+                    }
+
+                    match extract_symbols(&prefix, &s) {
+                        Ok(x) => symbols.extend(x),
+                        Err(e) => debug!("{crate_file:?}: {path:?}: Parse error: {e}"),
                     }
                 }
                 _ => continue,
             }
         }
 
-        Err(anyhow!("No Cargo.toml"))
+        symbols.sort();
+        symbols.dedup();
+        Ok(symbols)
     }
 }
 
@@ -210,8 +616,23 @@ struct CargoTomlProject {
 }
 
 #[derive(Deserialize)]
+#[serde(rename_all = "kebab-case")]
 struct Package {
     description: Option<String>,
+
+    // Lenient types like `readme`, so an unusual license doesn't fail the description lookup
+    license: Option<toml::Value>,
+    license_file: Option<toml::Value>,
+    readme: Option<toml::Value>,
+}
+
+/// Index file line with just the fields counted for older versions
+#[derive(Deserialize)]
+struct CrateVersion {
+    vers: Version,
+
+    #[serde(default)]
+    yanked: bool,
 }
 
 #[derive(Deserialize)]
@@ -0,0 +1,310 @@
+use {
+    crate::{functions::prefix, git},
+    log::*,
+    semver::Version,
+    serde::Deserialize,
+    std::{
+        collections::BTreeMap,
+        fs::{read_dir, read_to_string},
+        path::{Path, PathBuf},
+        sync::OnceLock,
+        time::SystemTime,
+    },
+};
+
+/// Download URL template markers defined by cargo's registry index format
+const MARKERS: [&str; 5] = [
+    "{crate}",
+    "{version}",
+    "{prefix}",
+    "{lowerprefix}",
+    "{sha256-checksum}",
+];
+
+/// Template of panamax's own `dl` URL, `<base url>/crates`, which it serves from its own layout
+const PANAMAX_TEMPLATE: &str = "crates/{crate}/{version}/download";
+
+/// Format of a mirror directory's index
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IndexKind {
+    /// Git checkout at `crates.io-index` (panamax)
+    Git,
+
+    /// Sparse-protocol index files under `index`
+    Sparse,
+
+    /// Cargo's own registry index directory (`~/.cargo/registry/index/*`) with `.cache` files
+    Cargo,
+}
+
+/**
+Layout of the index and crate files in a mirror directory
+
+The crate file location is derived from the `dl` field of the index's `config.json` if it
+describes where crate files live in the mirror directory, otherwise panamax's layout is assumed.
+*/
+#[derive(Clone, Debug)]
+pub struct Layout {
+    pub kind: IndexKind,
+    pub index_directory: PathBuf,
+    pub config_file: PathBuf,
+    mirror_directory: PathBuf,
+    crates_directory: PathBuf,
+    template: Option<String>,
+    downloaded: OnceLock<BTreeMap<String, Vec<Version>>>,
+}
+
+impl Layout {
+    /**
+    Detect the index format and read the `dl` template from its `config.json`
+
+    ```text
+    mirror/crates.io-index/config.json                     => Git
+    mirror/index/config.json or mirror/config.json + index => Sparse
+    mirror/config.json + mirror/.cache                     => Cargo
+    ```
+    */
+    pub fn load(mirror_directory: &Path) -> Layout {
+        let git = mirror_directory.join("crates.io-index");
+        let sparse = mirror_directory.join("index");
+        let cargo = mirror_directory.join(".cache");
+
+        let (kind, index_directory, config_file) = if git.is_dir() {
+            (IndexKind::Git, git.clone(), git.join("config.json"))
+        } else if sparse.is_dir() {
+            let config_file = if sparse.join("config.json").is_file() {
+                sparse.join("config.json")
+            } else {
+                mirror_directory.join("config.json")
+            };
+            (IndexKind::Sparse, sparse, config_file)
+        } else if cargo.is_dir() {
+            (
+                IndexKind::Cargo,
+                cargo,
+                mirror_directory.join("config.json"),
+            )
+        } else {
+            (IndexKind::Git, git.clone(), git.join("config.json"))
+        };
+
+        debug!("{kind:?} index directory {index_directory:?}");
+
+        let (crates_directory, template) = if kind == IndexKind::Cargo {
+            // `~/.cargo/registry/index/name-hash` => `~/.cargo/registry/cache/name-hash`
+            let crates_directory = match (mirror_directory.parent(), mirror_directory.file_name()) {
+                (Some(parent), Some(name)) => parent.with_file_name("cache").join(name),
+                _ => mirror_directory.to_path_buf(),
+            };
+            (
+                crates_directory,
+                Some(String::from("{crate}-{version}.crate")),
+            )
+        } else {
+            let template = match read_to_string(&config_file) {
+                Ok(s) => match serde_json::from_str::<Config>(&s) {
+                    Ok(config) => config.dl.as_deref().and_then(template),
+                    Err(e) => {
+                        debug!("{config_file:?}: Deserialization error: {e}");
+                        None
+                    }
+                },
+                Err(e) => {
+                    debug!("{config_file:?}: {e}");
+                    None
+                }
+            };
+            let template = template.filter(|x| {
+                // Decided once here rather than per crate file to save a stat per crate
+                let root = mirror_directory.join(template_root(x));
+                if x == PANAMAX_TEMPLATE {
+                    false
+                } else if root.is_dir() {
+                    true
+                } else {
+                    debug!("{root:?}: Does not exist; using panamax layout");
+                    false
+                }
+            });
+            (mirror_directory.to_path_buf(), template)
+        };
+
+        debug!("Crate file template: {template:?} in {crates_directory:?}");
+
+        Layout {
+            kind,
+            index_directory,
+            config_file,
+            mirror_directory: mirror_directory.to_path_buf(),
+            crates_directory,
+            template,
+            downloaded: OnceLock::new(),
+        }
+    }
+
+    /// Path to a crate's index file
+    pub fn index_file(&self, name: &str) -> PathBuf {
+        let name = name.to_lowercase();
+        self.index_directory.join(prefix(&name)).join(name)
+    }
+
+    /**
+    Commit checked out in a git index directory, recorded in the cache file to detect a sync
+    regardless of modification times
+
+    `None` for sparse and cargo indexes, or if the git HEAD can't be read.
+    */
+    pub fn head(&self) -> Option<String> {
+        (self.kind == IndexKind::Git)
+            .then(|| git::head(&self.index_directory))
+            .flatten()
+    }
+
+    /**
+    Modification time the cache file must be newer than to be considered fresh
+
+    The `config.json` file's modification time, or for cargo's registry directories, the later of
+    it and the crate file directory's, which changes whenever cargo downloads a crate.
+    */
+    pub fn modified(&self) -> std::io::Result<SystemTime> {
+        let config = self.config_file.metadata()?.modified()?;
+        if self.kind == IndexKind::Cargo {
+            Ok(config.max(self.crates_directory.metadata()?.modified()?))
+        } else {
+            Ok(config)
+        }
+    }
+
+    /**
+    Versions of a crate that cargo has downloaded, sorted ascending
+
+    Always empty unless the layout is [`IndexKind::Cargo`].
+    */
+    pub fn downloaded_versions(&self, name: &str) -> &[Version] {
+        self.downloaded
+            .get_or_init(|| {
+                let mut r = BTreeMap::<String, Vec<Version>>::new();
+                if self.kind == IndexKind::Cargo {
+                    for entry in read_dir(&self.crates_directory)
+                        .into_iter()
+                        .flatten()
+                        .flatten()
+                    {
+                        let file_name = entry.file_name();
+                        if let Some((name, version)) = file_name
+                            .to_str()
+                            .and_then(|x| x.strip_suffix(".crate"))
+                            .and_then(split_name_version)
+                        {
+                            r.entry(name.to_string()).or_default().push(version);
+                        }
+                    }
+                    for versions in r.values_mut() {
+                        versions.sort();
+                    }
+                }
+                r
+            })
+            .get(name)
+            .map(|x| x.as_slice())
+            .unwrap_or_default()
+    }
+
+    /**
+    Path to a crate file
+
+    Uses the `dl` template if its directory exists in the mirror directory, otherwise panamax's
+    layout.
+    */
+    pub fn crate_file(&self, name: &str, version: &str) -> PathBuf {
+        match &self.template {
+            Some(template) => self.crates_directory.join(
+                template
+                    .replace("{crate}", name)
+                    .replace("{version}", version)
+                    .replace("{prefix}", &prefix(name))
+                    .replace("{lowerprefix}", &prefix(&name.to_lowercase())),
+            ),
+            None => self.panamax_crate_file(name, version),
+        }
+    }
+
+    fn panamax_crate_file(&self, name: &str, version: &str) -> PathBuf {
+        let crates = self.mirror_directory.join("crates");
+
+        match name.len() {
+            // `mirror/crates/1/a/0.0.0/a-0.0.0.crate`
+            1 => crates.join("1").join(&name[..1]),
+
+            // `mirror/crates/2/aa/0.0.0/aa-0.0.0.crate`
+            2 => crates.join("2").join(&name[..2]),
+
+            // `mirror/crates/3/a/aaa/0.0.0/a-0.0.0.crate`
+            3 => crates.join("3").join(&name[..1]),
+
+            // `mirror/crates/aa/aa/0.0.0/aaaa-0.0.0.crate`
+            _ => crates.join(&name[..2]).join(&name[2..4]),
+        }
+        .join(name)
+        .join(version)
+        .join(format!("{name}-{version}.crate"))
+    }
+}
+
+/// Split a crate file stem like `foo-2d-0.1.0` into the crate name and version
+pub(crate) fn split_name_version(s: &str) -> Option<(&str, Version)> {
+    s.match_indices('-').find_map(|(i, _)| {
+        Version::parse(&s[i + 1..])
+            .ok()
+            .map(|version| (&s[..i], version))
+    })
+}
+
+#[derive(Deserialize)]
+struct Config {
+    dl: Option<String>,
+}
+
+/**
+Convert a `dl` URL into a crate file path template relative to the mirror directory
+
+```text
+https://host/crates/{prefix}/{crate}/{crate}-{version}.crate => crates/{prefix}/{crate}/{crate}-{version}.crate
+file:///srv/mirror                                           => /srv/mirror/{crate}/{version}/download
+```
+
+Like cargo, `/{crate}/{version}/download` is appended if the URL doesn't contain any markers.
+Templates using `{sha256-checksum}` are not supported because the cache doesn't store checksums.
+*/
+fn template(dl: &str) -> Option<String> {
+    let mut r = if let Some(path) = dl.strip_prefix("file://") {
+        path.to_string()
+    } else if let Some((_scheme, rest)) = dl.split_once("://") {
+        // Strip the host and leading slash so the path is relative to the mirror directory
+        rest.split_once('/')
+            .map(|(_host, path)| path.to_string())
+            .unwrap_or_default()
+    } else {
+        dl.trim_start_matches('/').to_string()
+    };
+
+    if r.contains("{sha256-checksum}") {
+        debug!("Unsupported dl template {dl:?}");
+        return None;
+    }
+
+    if !MARKERS.iter().any(|x| r.contains(x)) {
+        r = format!("{}/{{crate}}/{{version}}/download", r.trim_end_matches('/'));
+    }
+
+    Some(r)
+}
+
+/// Directory part of a template before the first marker, e.g. `files` for `files/{prefix}/{crate}`
+fn template_root(template: &str) -> String {
+    template
+        .split('/')
+        .take_while(|x| !MARKERS.iter().any(|marker| x.contains(marker)))
+        .collect::<Vec<_>>()
+        .join("/")
+}
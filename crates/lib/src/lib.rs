@@ -1,8 +1,38 @@
 #![doc = include_str!("../README.md")]
 
+mod cargo_config;
+mod description;
+mod details;
+mod diff;
+mod error;
+mod feed;
 mod functions;
+mod git;
+mod highlight;
 mod index;
 mod krate;
+mod layout;
+mod progress;
 mod search;
+mod source;
+mod stats;
+mod style;
+mod symbol;
 
-pub use index::Index;
+pub use {
+    cargo_config::CargoConfig,
+    description::{AffectedCrate, DescriptionReport, DescriptionStatus},
+    details::{Dependency, Details},
+    diff::{ChangedDescription, Diff, NewCrate, NewVersions, RemovedCrate, Yanked},
+    error::{Error, Result},
+    feed::{Feed, FeedEntry},
+    highlight::{Ansi, Highlighter, Html, Markdown},
+    index::Index,
+    krate::Crate,
+    progress::{Phase, Progress},
+    search::{Fields, Filter, Search, SearchBuilder, SearchOptions, SearchResults, Sort},
+    source::{DirectorySource, MemorySource, MirrorSource, TarballSource},
+    stats::{Stats, Tally},
+    style::{auto_color, highlight_style, Style},
+    symbol::{Symbol, SymbolKind, SymbolSearch},
+};
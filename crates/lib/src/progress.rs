@@ -0,0 +1,92 @@
+use std::{
+    fmt,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// Phase of a rebuild, in order
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Phase {
+    /// Walking the index directory for index files
+    IndexFiles,
+
+    /// Parsing each index file
+    Parse,
+
+    /// Opening each crate's crate file for its description (and public items)
+    CrateFiles,
+
+    /// Saving the cache file
+    Save,
+}
+
+impl fmt::Display for Phase {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Phase::IndexFiles => "Index files",
+            Phase::Parse => "Parse",
+            Phase::CrateFiles => "Crate files",
+            Phase::Save => "Save",
+        })
+    }
+}
+
+/**
+Progress of a rebuild phase, reported to the callback passed to [`Index::rebuild_with_progress`]
+or [`Index::from_source_with_progress`]
+
+Each phase is reported once with `done` 0 when it starts, then after each item, and is finished
+when `done` equals `total`. The total number of index files is unknown (`None`) until they have
+all been found. Items are processed in parallel, so the callback is called from multiple threads
+and may see `done` out of order.
+
+[`Index::rebuild_with_progress`]: crate::Index::rebuild_with_progress
+[`Index::from_source_with_progress`]: crate::Index::from_source_with_progress
+*/
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Progress {
+    pub phase: Phase,
+    pub done: usize,
+    pub total: Option<usize>,
+}
+
+impl Progress {
+    pub fn is_finished(&self) -> bool {
+        self.total == Some(self.done)
+    }
+}
+
+/// Reports a phase's start and each item done, from any thread
+pub(crate) struct Counter<'a> {
+    phase: Phase,
+    done: AtomicUsize,
+    total: Option<usize>,
+    callback: &'a (dyn Fn(Progress) + Sync),
+}
+
+impl<'a> Counter<'a> {
+    pub(crate) fn start(
+        phase: Phase,
+        total: Option<usize>,
+        callback: &'a (dyn Fn(Progress) + Sync),
+    ) -> Counter<'a> {
+        callback(Progress {
+            phase,
+            done: 0,
+            total,
+        });
+        Counter {
+            phase,
+            done: AtomicUsize::new(0),
+            total,
+            callback,
+        }
+    }
+
+    pub(crate) fn inc(&self) {
+        (self.callback)(Progress {
+            phase: self.phase,
+            done: self.done.fetch_add(1, Ordering::Relaxed) + 1,
+            total: self.total,
+        });
+    }
+}
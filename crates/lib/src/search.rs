@@ -1,10 +1,17 @@
 use {
-    crate::krate::Crate,
-    owo_colors::OwoColorize,
+    crate::{
+        error::Error,
+        highlight::{match_ranges, render, Highlighter},
+        krate::Crate,
+    },
+    anyhow::Result,
     rayon::prelude::*,
-    regex::{Regex, RegexBuilder, RegexSetBuilder},
-    semver::Version,
-    std::collections::{BTreeMap, HashSet},
+    regex::{Regex, RegexBuilder, RegexSet, RegexSetBuilder},
+    serde_json::json,
+    std::{
+        collections::{BTreeMap, HashSet},
+        sync::Arc,
+    },
 };
 
 pub struct Search {
@@ -15,11 +22,12 @@ pub struct Search {
 }
 
 impl Search {
+    /// Fails if a query is not a valid regular expression
     pub fn new(
         queries: &[String],
         case_insensitive: bool,
         crates: &BTreeMap<String, Crate>,
-    ) -> Search {
+    ) -> Result<Search, Error> {
         let mut names = HashSet::new();
 
         let mut name_exact = vec![];
@@ -33,8 +41,7 @@ impl Search {
         // Create a RegexSet with all queries
         let re = RegexSetBuilder::new(queries)
             .case_insensitive(case_insensitive)
-            .build()
-            .unwrap();
+            .build()?;
 
         // Filter matching names
         let name_contains = crates
@@ -72,94 +79,474 @@ impl Search {
             .collect::<Vec<_>>();
 
         // Convert the RegexSet to equivalent Vec<Regex>
-        let re = re
-            .patterns()
-            .iter()
-            .map(|x| {
-                RegexBuilder::new(x)
-                    .case_insensitive(case_insensitive)
-                    .build()
-                    .unwrap()
-            })
-            .collect::<Vec<_>>();
+        let re = regexes(re.patterns(), case_insensitive)?;
 
         // Return the search results
-        Search {
+        Ok(Search {
             name_exact,
             name_contains,
             desc_contains,
             re,
+        })
+    }
+
+    /// Format like `cargo search`, highlighting matches with the highlighter if any
+    pub fn to_string(&self, include_yanked: bool, highlighter: Option<&dyn Highlighter>) -> String {
+        to_text(self.iter(), &self.re, include_yanked, highlighter)
+    }
+
+    /// Results as a JSON array of objects with name, version, description, and registry
+    pub fn to_json(&self, include_yanked: bool) -> Result<String> {
+        to_json(self.iter(), include_yanked)
+    }
+
+    /// Keep only the first `limit` results in order of relevance
+    pub fn truncate(&mut self, limit: usize) {
+        let mut limit = limit;
+        for v in [
+            &mut self.name_exact,
+            &mut self.name_contains,
+            &mut self.desc_contains,
+        ] {
+            v.truncate(limit);
+            limit -= v.len();
         }
     }
 
-    pub fn to_string(&self, include_yanked: bool, highlight_matches: bool) -> String {
-        let mut width = 0;
-        let mut lines = vec![];
+    /// Crates whose names exactly match a query
+    pub fn name_exact(&self) -> &[Crate] {
+        &self.name_exact
+    }
 
-        // Collate results in order from each category and measure the widest name and version
-        for v in [&self.name_exact, &self.name_contains, &self.desc_contains] {
-            for crate_ in v {
-                let version = if include_yanked && crate_.latest.is_some() {
-                    crate_.latest.as_ref().unwrap().to_string()
-                } else {
-                    crate_
-                        .latest_ny
-                        .as_ref()
-                        .unwrap_or(&Version::new(0, 0, 0))
-                        .to_string()
-                    // Latest non-yanked version or "0.0.0" if all versions were yanked;
-                    // this matches `cargo search` behavior.
-                };
+    /// Crates whose names match a query, excluding exact matches
+    pub fn name_contains(&self) -> &[Crate] {
+        &self.name_contains
+    }
+
+    /// Crates whose descriptions match a query, excluding name matches
+    pub fn desc_contains(&self) -> &[Crate] {
+        &self.desc_contains
+    }
+
+    /// All matching crates in order of relevance
+    pub fn iter(&self) -> impl Iterator<Item = &Crate> {
+        self.name_exact
+            .iter()
+            .chain(self.name_contains.iter())
+            .chain(self.desc_contains.iter())
+    }
+
+    /// Number of matching crates
+    pub fn len(&self) -> usize {
+        self.name_exact.len() + self.name_contains.len() + self.desc_contains.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn to_vec(&self) -> Vec<Crate> {
+        self.iter().cloned().collect()
+    }
+}
+
+/// Format crates like `cargo search`, highlighting matches of the patterns with the highlighter if any
+fn to_text<'a>(
+    crates: impl Iterator<Item = &'a Crate>,
+    re: &[Regex],
+    include_yanked: bool,
+    highlighter: Option<&dyn Highlighter>,
+) -> String {
+    let mut width = 0;
+    let mut lines = vec![];
+
+    // Collate results in order and measure the widest name and version
+    for crate_ in crates {
+        // Include any same-named crates from other registries
+        for (crate_, tag) in crate_.tagged() {
+            let name_and_version = format!(
+                "{} = \"{}\"    ",
+                crate_.name,
+                crate_.version(include_yanked)
+            );
+
+            width = width.max(name_and_version.len());
 
-                let name_and_version = format!("{} = \"{version}\"    ", crate_.name);
+            lines.push((name_and_version, tag, &crate_.description));
+        }
+    }
 
-                width = width.max(name_and_version.len());
+    let highlight =
+        |s: &str, highlighter: &dyn Highlighter| render(s, &match_ranges(re, s), highlighter);
 
-                lines.push((name_and_version, &crate_.description));
+    // Build result string
+    lines
+        .par_iter()
+        .map(|(name_and_version, tag, description)| {
+            let s = " ".repeat(width - name_and_version.len());
+            let tag = tag.as_ref().map(|x| format!("{x} ")).unwrap_or_default();
+            let description = description
+                .as_ref()
+                .map(|d| d.replace("\n", "\\n").replace("\r", "\\r"));
+            let (nv, tag, d) = match highlighter {
+                Some(highlighter) => (
+                    highlight(name_and_version, highlighter),
+                    highlighter.text(&tag),
+                    description.map(|d| highlight(&d, highlighter)),
+                ),
+                None => (name_and_version.clone(), tag, description),
+            };
+            if let Some(d) = d {
+                format!("{nv}{s}# {tag}{d}\n")
+            } else if !tag.is_empty() {
+                format!("{nv}{s}# {}\n", tag.trim_end())
+            } else {
+                format!("{nv}\n")
             }
+        })
+        .collect::<Vec<_>>()
+        .join("")
+}
+
+/// Crates as a JSON array of objects with name, version, description, and registry
+fn to_json<'a>(crates: impl Iterator<Item = &'a Crate>, include_yanked: bool) -> Result<String> {
+    let results = crates
+        .flat_map(|crate_| crate_.tagged())
+        .map(|(crate_, _tag)| {
+            json!({
+                "name": crate_.name,
+                "version": crate_.version(include_yanked).to_string(),
+                "description": crate_.description,
+                "registry": crate_.registry,
+            })
+        })
+        .collect::<Vec<_>>();
+    Ok(serde_json::to_string_pretty(&results)?)
+}
+
+/// Crate fields that queries are matched against
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Fields {
+    #[default]
+    All,
+    Name,
+    Description,
+}
+
+impl Fields {
+    fn name(&self) -> bool {
+        matches!(self, Fields::All | Fields::Name)
+    }
+
+    fn description(&self) -> bool {
+        matches!(self, Fields::All | Fields::Description)
+    }
+}
+
+/// Order of search results
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Sort {
+    /// Exact name matches, then name matches, then description matches, like `cargo search`
+    #[default]
+    Relevance,
+
+    /// Crate name
+    Name,
+}
+
+/// Predicate a crate must satisfy to be included in search results
+pub type Filter = Arc<dyn Fn(&Crate) -> bool + Send + Sync>;
+
+/**
+Options for [`crate::Index::search_with`]
+
+By default, queries are case sensitive regular expressions matched against names and descriptions,
+ordered by relevance, showing the latest non-yanked version. Set `case_insensitive` to match like
+`cargo search` and the `panamax-search` command, which ignore case by default.
+*/
+#[derive(Clone, Default)]
+pub struct SearchOptions {
+    pub case_insensitive: bool,
+
+    /// Match queries as literal strings instead of regular expressions
+    pub literal: bool,
+
+    pub fields: Fields,
+
+    /// Show the latest version even if it was yanked
+    pub include_yanked: bool,
+
+    /// Maximum number of results
+    pub limit: Option<usize>,
+
+    pub sort: Sort,
+
+    /// Predicates that every result must satisfy
+    pub filters: Vec<Filter>,
+}
+
+/**
+Builds [`SearchOptions`] and runs the search
+
+```
+# use {panamax_search_lib::{Fields, Index, MemorySource}, std::sync::Arc};
+# fn main() -> panamax_search_lib::Result<()> {
+# let mut source = MemorySource::default();
+# source.add_index_file("serde", r#"{"name":"serde","vers":"1.0.0","deps":[],"features":{},"yanked":false}"#);
+# let index = Index::from_source(Arc::new(source), false)?;
+let results = index
+    .search_builder(&["serde".to_string()])
+    .case_insensitive(true)
+    .fields(Fields::Name)
+    .filter(|x| x.latest_non_yanked().is_some())
+    .limit(10)
+    .build()?;
+for crate_ in &results {
+    println!("{}", crate_.name());
+}
+# assert_eq!(results.count(), 1);
+# Ok(())
+# }
+```
+*/
+pub struct SearchBuilder<'a> {
+    crates: &'a BTreeMap<String, Crate>,
+    queries: Vec<String>,
+    options: SearchOptions,
+}
+
+impl<'a> SearchBuilder<'a> {
+    pub(crate) fn new(queries: &[String], crates: &'a BTreeMap<String, Crate>) -> Self {
+        SearchBuilder {
+            crates,
+            queries: queries.to_vec(),
+            options: SearchOptions::default(),
         }
+    }
 
-        // Build result string
-        lines
-            .par_iter()
-            .map(|(name_and_version, description)| {
-                if let Some(d) = description {
-                    let (nv, d) = if highlight_matches {
-                        (
-                            &self.highlight(name_and_version),
-                            &self.highlight(&d.replace("\n", "\\n").replace("\r", "\\r")),
-                        )
-                    } else {
-                        (name_and_version, d)
-                    };
-                    let s = " ".repeat(width - name_and_version.len());
-                    format!("{nv}{s}# {d}\n")
-                } else if highlight_matches {
-                    format!("{}\n", &self.highlight(name_and_version))
+    /// Replace all options
+    pub fn options(mut self, options: SearchOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    pub fn case_insensitive(mut self, case_insensitive: bool) -> Self {
+        self.options.case_insensitive = case_insensitive;
+        self
+    }
+
+    pub fn literal(mut self, literal: bool) -> Self {
+        self.options.literal = literal;
+        self
+    }
+
+    pub fn fields(mut self, fields: Fields) -> Self {
+        self.options.fields = fields;
+        self
+    }
+
+    pub fn include_yanked(mut self, include_yanked: bool) -> Self {
+        self.options.include_yanked = include_yanked;
+        self
+    }
+
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.options.limit = Some(limit);
+        self
+    }
+
+    pub fn sort(mut self, sort: Sort) -> Self {
+        self.options.sort = sort;
+        self
+    }
+
+    /// Add a predicate that every result must satisfy
+    pub fn filter(mut self, predicate: impl Fn(&Crate) -> bool + Send + Sync + 'static) -> Self {
+        self.options.filters.push(Arc::new(predicate));
+        self
+    }
+
+    /// Compile the queries and match every crate; fails if a query is not a valid regular
+    /// expression
+    pub fn build(self) -> Result<SearchResults<'a>, Error> {
+        SearchResults::new(self.crates, self.queries, self.options)
+    }
+}
+
+/// Relevance of a search result
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Category {
+    NameExact,
+    NameContains,
+    DescContains,
+}
+
+/**
+Search results, evaluated eagerly
+
+Every crate is matched once, in parallel, when the results are built, so even with a `limit` the
+whole index is searched; iterating and counting the results afterwards is cheap.
+*/
+pub struct SearchResults<'a> {
+    crates: Vec<&'a Crate>,
+
+    /// Relevance of each result in `crates`
+    categories: Vec<Category>,
+
+    re: Vec<Regex>,
+    options: SearchOptions,
+}
+
+impl<'a> SearchResults<'a> {
+    fn new(
+        crates: &'a BTreeMap<String, Crate>,
+        queries: Vec<String>,
+        options: SearchOptions,
+    ) -> Result<SearchResults<'a>, Error> {
+        let patterns = queries
+            .iter()
+            .map(|x| {
+                if options.literal {
+                    regex::escape(x)
                 } else {
-                    format!("{name_and_version}\n")
+                    x.clone()
                 }
             })
-            .collect::<Vec<_>>()
-            .join("")
+            .collect::<Vec<_>>();
+        let set = RegexSetBuilder::new(&patterns)
+            .case_insensitive(options.case_insensitive)
+            .build()?;
+        let re = regexes(&patterns, options.case_insensitive)?;
+
+        // Categorize every crate once, in name order
+        let mut matches = crates
+            .par_iter()
+            .filter_map(|(_name, crate_)| {
+                category(crate_, &queries, &set, &options).map(|x| (x, crate_))
+            })
+            .collect::<Vec<_>>();
+
+        if options.sort == Sort::Relevance {
+            // Exact name matches in query order, then name matches, then description matches
+            let mut exact = queries
+                .iter()
+                .filter_map(|x| crates.get(x))
+                .filter(|x| category(x, &queries, &set, &options) == Some(Category::NameExact))
+                .map(|x| (Category::NameExact, x))
+                .collect::<Vec<_>>();
+            let mut seen = HashSet::new();
+            exact.retain(|(_category, x)| seen.insert(&x.name));
+            matches.retain(|(category, _crate_)| *category != Category::NameExact);
+            matches.sort_by_key(|(category, _crate_)| *category);
+            exact.append(&mut matches);
+            matches = exact;
+        }
+        if let Some(limit) = options.limit {
+            matches.truncate(limit);
+        }
+
+        let (categories, crates) = matches.into_iter().unzip();
+        Ok(SearchResults {
+            crates,
+            categories,
+            re,
+            options,
+        })
+    }
+
+    pub fn options(&self) -> &SearchOptions {
+        &self.options
+    }
+
+    /// Matching crates in order, up to the limit
+    pub fn iter(&self) -> impl Iterator<Item = &'a Crate> + '_ {
+        self.crates.iter().copied()
+    }
+
+    /// Number of results up to the limit
+    pub fn count(&self) -> usize {
+        self.crates.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.crates.is_empty()
     }
 
-    fn highlight(&self, s: &str) -> String {
-        let mut r = s.to_string();
-        for re in &self.re {
-            for m in re.find_iter(s).map(|m| m.as_str()) {
-                r = r.replace(m, &m.green().bold().to_string());
+    /// Collect the results into a [`Search`] categorized by relevance
+    pub fn to_search(&self) -> Search {
+        let mut r = Search {
+            name_exact: vec![],
+            name_contains: vec![],
+            desc_contains: vec![],
+            re: self.re.clone(),
+        };
+        for (crate_, category) in self.crates.iter().zip(&self.categories) {
+            match category {
+                Category::NameExact => r.name_exact.push((*crate_).clone()),
+                Category::NameContains => r.name_contains.push((*crate_).clone()),
+                Category::DescContains => r.desc_contains.push((*crate_).clone()),
             }
         }
         r
     }
 
-    pub fn to_vec(&self) -> Vec<Crate> {
-        self.name_exact
-            .iter()
-            .chain(self.name_contains.iter())
-            .chain(self.desc_contains.iter())
-            .cloned()
-            .collect()
+    /// Format like `cargo search` in the sort order, highlighting matches with the highlighter if any
+    pub fn to_string(&self, highlighter: Option<&dyn Highlighter>) -> String {
+        to_text(
+            self.iter(),
+            &self.re,
+            self.options.include_yanked,
+            highlighter,
+        )
+    }
+
+    /// Results as a JSON array of objects with name, version, description, and registry
+    pub fn to_json(&self) -> Result<String> {
+        to_json(self.iter(), self.options.include_yanked)
     }
 }
+
+impl<'a, 'b> IntoIterator for &'b SearchResults<'a> {
+    type Item = &'a Crate;
+    type IntoIter = std::iter::Copied<std::slice::Iter<'b, &'a Crate>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.crates.iter().copied()
+    }
+}
+
+/// How well a crate matches, or `None` if it doesn't match or is filtered out
+fn category(
+    crate_: &Crate,
+    queries: &[String],
+    set: &RegexSet,
+    options: &SearchOptions,
+) -> Option<Category> {
+    let fields = options.fields;
+    let category = if fields.name() && queries.contains(&crate_.name) {
+        Category::NameExact
+    } else if fields.name() && set.is_match(&crate_.name) {
+        Category::NameContains
+    } else if fields.description() && crate_.description.as_ref().is_some_and(|x| set.is_match(x)) {
+        Category::DescContains
+    } else {
+        return None;
+    };
+    options
+        .filters
+        .iter()
+        .all(|f| f(crate_))
+        .then_some(category)
+}
+
+/// A regular expression for each pattern, to highlight matches
+fn regexes(patterns: &[String], case_insensitive: bool) -> Result<Vec<Regex>, Error> {
+    Ok(patterns
+        .iter()
+        .map(|x| {
+            RegexBuilder::new(x)
+                .case_insensitive(case_insensitive)
+                .build()
+        })
+        .collect::<Result<Vec<_>, _>>()?)
+}
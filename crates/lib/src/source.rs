@@ -0,0 +1,389 @@
+use {
+    crate::{
+        error::Error,
+        functions::{filter_entries, prefix},
+        layout::{split_name_version, IndexKind, Layout},
+    },
+    flate2::read::GzDecoder,
+    semver::Version,
+    std::{
+        collections::{BTreeMap, HashMap},
+        fmt,
+        fs::{metadata, read, File},
+        io::{self, BufReader, Cursor, Read, Seek, SeekFrom},
+        path::{Path, PathBuf},
+    },
+    walkdir::WalkDir,
+};
+
+/**
+Where a mirror's index files and crate files come from
+
+Index files are identified by crate name; their file names are the lowercase crate names, like
+`se/rd/serde`.
+*/
+pub trait MirrorSource: fmt::Debug + Send + Sync {
+    /// File names of all index files, i.e. the lowercase crate names
+    fn index_files(&self) -> io::Result<Vec<String>>;
+
+    /// Like [`MirrorSource::index_files`], but call `found` after each index file is found
+    fn index_files_with_progress(&self, found: &(dyn Fn() + Sync)) -> io::Result<Vec<String>> {
+        let r = self.index_files()?;
+        r.iter().for_each(|_| found());
+        Ok(r)
+    }
+
+    /// Contents of a crate's index file: JSON lines, oldest version first, or a cargo `.cache` file
+    fn read_index_file(&self, name: &str) -> io::Result<Vec<u8>>;
+
+    /// Open a crate file (`.crate` gzipped tarball)
+    fn open_crate_file(&self, name: &str, version: &Version) -> io::Result<Box<dyn Read + '_>>;
+
+    /// Size of a crate file in bytes
+    fn crate_file_size(&self, name: &str, version: &Version) -> io::Result<u64> {
+        io::copy(&mut self.open_crate_file(name, version)?, &mut io::sink())
+    }
+
+    /// Path of a crate's index file, used in error messages
+    fn index_file_path(&self, name: &str) -> PathBuf {
+        PathBuf::from(index_path(name))
+    }
+
+    /**
+    Versions that have a crate file, sorted ascending, if the source only has some of them, like
+    cargo's registry cache
+
+    `None` means every version in the index file has a crate file.
+    */
+    fn crate_versions(&self, _name: &str) -> Option<Vec<Version>> {
+        None
+    }
+}
+
+/**
+Path of a crate's index file relative to the index directory
+
+```text
+serde => se/rd/serde
+```
+*/
+pub(crate) fn index_path(name: &str) -> String {
+    let name = name.to_lowercase();
+    format!("{}/{name}", prefix(&name))
+}
+
+/// Mirror directory on the filesystem with a git, sparse, or cargo registry index
+#[derive(Debug)]
+pub struct DirectorySource {
+    pub(crate) layout: Layout,
+}
+
+impl DirectorySource {
+    /// Detect the mirror directory's index format
+    pub fn new(mirror_directory: &Path) -> DirectorySource {
+        DirectorySource {
+            layout: Layout::load(mirror_directory),
+        }
+    }
+
+    /// Directory containing the index files, watched for changes by a sync
+    pub fn index_directory(&self) -> &Path {
+        &self.layout.index_directory
+    }
+
+    /// Index `config.json` file, whose modification time marks the end of a sync
+    pub fn config_file(&self) -> &Path {
+        &self.layout.config_file
+    }
+}
+
+impl MirrorSource for DirectorySource {
+    fn index_files(&self) -> io::Result<Vec<String>> {
+        self.index_files_with_progress(&|| {})
+    }
+
+    fn index_files_with_progress(&self, found: &(dyn Fn() + Sync)) -> io::Result<Vec<String>> {
+        Ok(WalkDir::new(&self.layout.index_directory)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_entry(filter_entries)
+            .flatten()
+            .filter(|x| x.file_type().is_file())
+            .map(|x| {
+                found();
+                x.file_name().to_string_lossy().to_string()
+            })
+            .collect())
+    }
+
+    fn read_index_file(&self, name: &str) -> io::Result<Vec<u8>> {
+        read(self.layout.index_file(name))
+    }
+
+    fn open_crate_file(&self, name: &str, version: &Version) -> io::Result<Box<dyn Read + '_>> {
+        Ok(Box::new(File::open(
+            self.layout.crate_file(name, &version.to_string()),
+        )?))
+    }
+
+    fn crate_file_size(&self, name: &str, version: &Version) -> io::Result<u64> {
+        Ok(metadata(self.layout.crate_file(name, &version.to_string()))?.len())
+    }
+
+    fn index_file_path(&self, name: &str) -> PathBuf {
+        self.layout.index_file(name)
+    }
+
+    fn crate_versions(&self, name: &str) -> Option<Vec<Version>> {
+        // Cargo only has the crate files it has downloaded
+        (self.layout.kind == IndexKind::Cargo)
+            .then(|| self.layout.downloaded_versions(name).to_vec())
+    }
+}
+
+/**
+Mirror held in memory, e.g. for tests
+
+```
+# use {panamax_search_lib::{Index, MemorySource}, semver::Version, std::sync::Arc};
+# fn main() -> Result<(), Box<dyn std::error::Error>> {
+# let mut tarball = tar::Builder::new(flate2::write::GzEncoder::new(vec![], Default::default()));
+# let cargo_toml = "[package]\nname = \"foo\"\nversion = \"0.1.0\"\ndescription = \"Foo\"\n";
+# let mut header = tar::Header::new_gnu();
+# header.set_size(cargo_toml.len() as u64);
+# header.set_cksum();
+# tarball.append_data(&mut header, "foo-0.1.0/Cargo.toml", cargo_toml.as_bytes())?;
+# let crate_file_bytes = tarball.into_inner()?.finish()?;
+let mut source = MemorySource::default();
+source.add_index_file("foo", r#"{"name":"foo","vers":"0.1.0","deps":[],"features":{},"yanked":false}"#);
+source.add_crate_file("foo", Version::new(0, 1, 0), crate_file_bytes);
+let index = Index::from_source(Arc::new(source), false)?;
+assert_eq!(index.get("foo").unwrap().description(), Some("Foo"));
+# Ok(())
+# }
+```
+*/
+#[derive(Default)]
+pub struct MemorySource {
+    index_files: BTreeMap<String, Vec<u8>>,
+    crate_files: HashMap<(String, Version), Vec<u8>>,
+}
+
+impl MemorySource {
+    /// Add or replace a crate's index file
+    pub fn add_index_file(&mut self, name: &str, data: impl Into<Vec<u8>>) {
+        self.index_files.insert(name.to_lowercase(), data.into());
+    }
+
+    /// Add or replace a crate file
+    pub fn add_crate_file(&mut self, name: &str, version: Version, data: impl Into<Vec<u8>>) {
+        self.crate_files
+            .insert((name.to_string(), version), data.into());
+    }
+}
+
+impl fmt::Debug for MemorySource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("MemorySource")
+            .field("index_files", &self.index_files.len())
+            .field("crate_files", &self.crate_files.len())
+            .finish()
+    }
+}
+
+impl MirrorSource for MemorySource {
+    fn index_files(&self) -> io::Result<Vec<String>> {
+        Ok(self.index_files.keys().cloned().collect())
+    }
+
+    fn read_index_file(&self, name: &str) -> io::Result<Vec<u8>> {
+        self.index_files
+            .get(&name.to_lowercase())
+            .cloned()
+            .ok_or_else(|| not_found(&index_path(name)))
+    }
+
+    fn open_crate_file(&self, name: &str, version: &Version) -> io::Result<Box<dyn Read + '_>> {
+        match self.crate_files.get(&(name.to_string(), version.clone())) {
+            Some(data) => Ok(Box::new(Cursor::new(data))),
+            None => Err(not_found(&format!("{name}-{version}.crate"))),
+        }
+    }
+}
+
+/// Location of a crate file in a tarball
+enum TarballEntry {
+    /// Byte range in an uncompressed tarball
+    Range { start: u64, size: u64 },
+
+    /// Contents read from a compressed tarball
+    Data(Vec<u8>),
+}
+
+/**
+Tarball of a mirror directory (`.tar`, or `.tar.gz` / `.tgz`), e.g. a mirror shipped as a single
+archive
+
+The index files are read into memory when the tarball is opened. Crate files are read on demand
+from an uncompressed tarball, but must also be read into memory from a compressed one, which is
+refused once its crate files exceed [`TarballSource::MEMORY_LIMIT`]; decompress large mirrors
+first.
+
+Index files are found under the first `crates.io-index` or `index` directory in the tarball, and
+crate files by their names, like `serde-1.0.0.crate` or `serde/1.0.0/download`.
+*/
+pub struct TarballSource {
+    path: PathBuf,
+    index_files: BTreeMap<String, Vec<u8>>,
+    crate_files: HashMap<(String, Version), TarballEntry>,
+}
+
+impl TarballSource {
+    /// Default limit for the crate files read into memory from a compressed tarball: 1 GiB
+    pub const MEMORY_LIMIT: u64 = 1 << 30;
+
+    pub fn open(path: &Path) -> Result<TarballSource, Error> {
+        TarballSource::open_with_memory_limit(path, TarballSource::MEMORY_LIMIT)
+    }
+
+    /// Like [`TarballSource::open`], with a limit in bytes for the crate files read into memory
+    /// from a compressed tarball
+    pub fn open_with_memory_limit(path: &Path, limit: u64) -> Result<TarballSource, Error> {
+        let mut f = File::open(path).map_err(Error::io(path))?;
+        let mut magic = [0; 2];
+        let compressed = f.read_exact(&mut magic).is_ok() && magic == [0x1f, 0x8b];
+        f.rewind().map_err(Error::io(path))?;
+
+        let reader: Box<dyn Read> = if compressed {
+            Box::new(GzDecoder::new(BufReader::new(f)))
+        } else {
+            Box::new(BufReader::new(f))
+        };
+        let mut archive = tar::Archive::new(reader);
+
+        let mut r = TarballSource {
+            path: path.to_path_buf(),
+            index_files: BTreeMap::new(),
+            crate_files: HashMap::new(),
+        };
+        let mut in_memory = 0;
+        for entry in archive.entries().map_err(Error::io(path))? {
+            let mut entry = entry.map_err(Error::io(path))?;
+            if !entry.header().entry_type().is_file() {
+                continue;
+            }
+            let components = entry
+                .path()
+                .map_err(Error::io(path))?
+                .components()
+                .map(|x| x.as_os_str().to_string_lossy().to_string())
+                .collect::<Vec<_>>();
+
+            if let Some(key) = crate_file_key(&components) {
+                let location = if compressed {
+                    in_memory += entry.size();
+                    if in_memory > limit {
+                        return Err(Error::TarballTooLarge {
+                            path: path.to_path_buf(),
+                            limit,
+                        });
+                    }
+                    let mut data = vec![];
+                    entry.read_to_end(&mut data).map_err(Error::io(path))?;
+                    TarballEntry::Data(data)
+                } else {
+                    TarballEntry::Range {
+                        start: entry.raw_file_position(),
+                        size: entry.size(),
+                    }
+                };
+                r.crate_files.insert(key, location);
+            } else if is_index_file(&components) {
+                let mut data = vec![];
+                entry.read_to_end(&mut data).map_err(Error::io(path))?;
+                r.index_files
+                    .insert(components.last().unwrap().to_lowercase(), data);
+            }
+        }
+
+        Ok(r)
+    }
+}
+
+/// Crate name and version of a crate file path like `.../serde-1.0.0.crate` or
+/// `.../serde/1.0.0/download`
+fn crate_file_key(components: &[String]) -> Option<(String, Version)> {
+    match components {
+        [.., name, version, download] if download == "download" => {
+            Some((name.clone(), Version::parse(version).ok()?))
+        }
+        [.., file_name] => file_name
+            .strip_suffix(".crate")
+            .and_then(split_name_version)
+            .map(|(name, version)| (name.to_string(), version)),
+        [] => None,
+    }
+}
+
+/// Whether a path is an index file, i.e. in a prefix directory of the first index directory
+fn is_index_file(components: &[String]) -> bool {
+    let Some(i) = components
+        .iter()
+        .position(|x| x == "crates.io-index" || x == "index")
+    else {
+        return false;
+    };
+    let rest = &components[i + 1..];
+    rest.len() > 1 && !rest.iter().any(|x| x.starts_with('.'))
+}
+
+impl fmt::Debug for TarballSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("TarballSource")
+            .field("path", &self.path)
+            .field("index_files", &self.index_files.len())
+            .field("crate_files", &self.crate_files.len())
+            .finish()
+    }
+}
+
+impl MirrorSource for TarballSource {
+    fn index_files(&self) -> io::Result<Vec<String>> {
+        Ok(self.index_files.keys().cloned().collect())
+    }
+
+    fn read_index_file(&self, name: &str) -> io::Result<Vec<u8>> {
+        self.index_files
+            .get(&name.to_lowercase())
+            .cloned()
+            .ok_or_else(|| not_found(&index_path(name)))
+    }
+
+    fn open_crate_file(&self, name: &str, version: &Version) -> io::Result<Box<dyn Read + '_>> {
+        match self.crate_files.get(&(name.to_string(), version.clone())) {
+            Some(TarballEntry::Range { start, size }) => {
+                let mut f = File::open(&self.path)?;
+                f.seek(SeekFrom::Start(*start))?;
+                Ok(Box::new(f.take(*size)))
+            }
+            Some(TarballEntry::Data(data)) => Ok(Box::new(Cursor::new(data))),
+            None => Err(not_found(&format!("{name}-{version}.crate"))),
+        }
+    }
+
+    fn crate_file_size(&self, name: &str, version: &Version) -> io::Result<u64> {
+        match self.crate_files.get(&(name.to_string(), version.clone())) {
+            Some(TarballEntry::Range { size, .. }) => Ok(*size),
+            Some(TarballEntry::Data(data)) => Ok(data.len() as u64),
+            None => Err(not_found(&format!("{name}-{version}.crate"))),
+        }
+    }
+
+    fn index_file_path(&self, name: &str) -> PathBuf {
+        self.path.join(index_path(name))
+    }
+}
+
+fn not_found(path: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::NotFound, format!("{path}: Not found"))
+}
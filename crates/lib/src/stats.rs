@@ -0,0 +1,303 @@
+use {
+    crate::krate::Crate,
+    anyhow::Result,
+    serde::{Deserialize, Serialize},
+    std::{collections::HashMap, fmt},
+};
+
+/**
+Totals and distributions over the crates of an index
+
+Computed from the cache file: the distributions come from the `Cargo.toml` of each crate's latest
+version and the sizes from the crate files of all versions, both recorded by the rebuild. Crates
+loaded from an older cache file only count towards the crates and versions until the next rebuild.
+*/
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct Stats {
+    pub crates: usize,
+
+    /// Versions including yanked versions; zero for crates loaded from an older cache file
+    pub versions: usize,
+
+    pub yanked_versions: usize,
+
+    /// Crates whose versions are all yanked
+    pub yanked_only: usize,
+
+    pub missing_descriptions: usize,
+
+    /// Crates whose `Cargo.toml` could be read, which the distributions below are based on
+    pub manifests: usize,
+
+    /// Most common `license` fields, most common first; `None` if the crate has no license
+    pub licenses: Vec<Tally>,
+
+    /// Crates per `edition`, where a missing edition is 2015, newest first
+    pub editions: Vec<Tally>,
+
+    /// Crates per `rust-version` (MSRV) as `major.minor`, newest first; `None` if unspecified
+    pub rust_versions: Vec<Tally>,
+
+    /// Most common `categories`, most common first
+    pub categories: Vec<Tally>,
+
+    /// Most common `keywords`, most common first
+    pub keywords: Vec<Tally>,
+
+    /// Crate files of all versions in the mirror
+    pub crate_files: usize,
+
+    /// Total size of the crate files in bytes
+    pub crate_file_size: u64,
+}
+
+/// Number of crates with a value
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Tally {
+    pub value: Option<String>,
+    pub count: usize,
+}
+
+/// Fields of a `Cargo.toml` `[package]` (or `[project]`) section, normalized, as saved in the
+/// cache file
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub(crate) struct Manifest {
+    #[serde(rename = "l", skip_serializing_if = "Option::is_none")]
+    license: Option<String>,
+
+    #[serde(rename = "e")]
+    edition: String,
+
+    #[serde(rename = "r", skip_serializing_if = "Option::is_none")]
+    rust_version: Option<String>,
+
+    #[serde(rename = "c", default, skip_serializing_if = "Vec::is_empty")]
+    categories: Vec<String>,
+
+    #[serde(rename = "k", default, skip_serializing_if = "Vec::is_empty")]
+    keywords: Vec<String>,
+}
+
+/// `Cargo.toml` with lenient types so unusual manifests still count
+#[derive(Deserialize)]
+struct CargoToml {
+    package: Option<toml::Table>,
+    project: Option<toml::Table>,
+}
+
+impl Stats {
+    /// Statistics of crates from one source; `top` limits the licenses, categories, and keywords
+    pub fn new<'a>(crates: impl Iterator<Item = &'a Crate>, top: usize) -> Stats {
+        let mut r = Stats::default();
+        let mut licenses = HashMap::new();
+        let mut editions = HashMap::new();
+        let mut rust_versions = HashMap::new();
+        let mut categories = HashMap::new();
+        let mut keywords = HashMap::new();
+        for crate_ in crates {
+            r.crates += 1;
+            r.versions += crate_.versions;
+            r.yanked_versions += crate_.yanked;
+            if crate_.latest_ny.is_none() {
+                r.yanked_only += 1;
+            }
+            if crate_.description.is_none() {
+                r.missing_descriptions += 1;
+            }
+            r.crate_files += crate_.crate_files;
+            r.crate_file_size += crate_.crate_file_size;
+
+            let Some(manifest) = &crate_.manifest else {
+                continue;
+            };
+            r.manifests += 1;
+            *licenses.entry(manifest.license.clone()).or_insert(0) += 1;
+            *editions.entry(Some(manifest.edition.clone())).or_insert(0) += 1;
+            *rust_versions
+                .entry(manifest.rust_version.clone())
+                .or_insert(0) += 1;
+            for category in &manifest.categories {
+                *categories.entry(Some(category.clone())).or_insert(0) += 1;
+            }
+            for keyword in &manifest.keywords {
+                *keywords.entry(Some(keyword.clone())).or_insert(0) += 1;
+            }
+        }
+
+        r.licenses = most_common(licenses, top);
+        r.editions = newest_first(editions);
+        r.rust_versions = newest_first(rust_versions);
+        r.categories = most_common(categories, top);
+        r.keywords = most_common(keywords, top);
+        r
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+}
+
+impl Manifest {
+    pub(crate) fn parse(s: &str) -> Option<Manifest> {
+        let toml = toml::from_str::<CargoToml>(s).ok()?;
+        let package = toml.package.or(toml.project)?;
+        let string = |key: &str| package.get(key).and_then(|x| x.as_str()).map(String::from);
+        let strings = |key: &str| {
+            package
+                .get(key)
+                .and_then(|x| x.as_array())
+                .map(|x| {
+                    x.iter()
+                        .filter_map(|x| x.as_str())
+                        .map(|x| x.to_lowercase())
+                        .collect::<Vec<_>>()
+                })
+                .unwrap_or_default()
+        };
+
+        Some(Manifest {
+            license: string("license")
+                .or_else(|| string("license-file").map(|_| String::from("(license file)"))),
+            edition: string("edition").unwrap_or_else(|| String::from("2015")),
+            rust_version: string("rust-version").map(|x| minor_version(&x)),
+            categories: strings("categories"),
+            keywords: strings("keywords"),
+        })
+    }
+}
+
+/// `1.56.1` => `1.56`
+fn minor_version(s: &str) -> String {
+    s.trim()
+        .splitn(3, '.')
+        .take(2)
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+/// Highest counts first, then by value
+fn most_common(counts: HashMap<Option<String>, usize>, top: usize) -> Vec<Tally> {
+    let mut r = tallies(counts);
+    r.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.value.cmp(&b.value)));
+    r.truncate(top);
+    r
+}
+
+/// Newest version-like value first, e.g. `2021` before `2018` and `1.70` before `1.9`, and `None`
+/// last
+fn newest_first(counts: HashMap<Option<String>, usize>) -> Vec<Tally> {
+    let key = |x: &Tally| {
+        x.value.as_ref().map(|x| {
+            x.split('.')
+                .map(|x| x.parse::<u64>().unwrap_or(0))
+                .collect::<Vec<_>>()
+        })
+    };
+    let mut r = tallies(counts);
+    r.sort_by(|a, b| match (key(a), key(b)) {
+        (Some(a), Some(b)) => b.cmp(&a),
+        (a, b) => a.is_none().cmp(&b.is_none()),
+    });
+    r
+}
+
+fn tallies(counts: HashMap<Option<String>, usize>) -> Vec<Tally> {
+    counts
+        .into_iter()
+        .map(|(value, count)| Tally { value, count })
+        .collect()
+}
+
+/// Size like `1.5 GiB`
+fn size(bytes: u64) -> String {
+    let mut size = bytes as f64;
+    for unit in ["B", "KiB", "MiB", "GiB"] {
+        if size < 1024.0 {
+            return if unit == "B" {
+                format!("{bytes} B")
+            } else {
+                format!("{size:.1} {unit}")
+            };
+        }
+        size /= 1024.0;
+    }
+    format!("{size:.1} TiB")
+}
+
+/**
+Text report
+
+```text
+Crates: 152340 (1204 yanked only, 275 without a description)
+Versions: 1523400 (12034 yanked)
+Crate files: 1523389 (123.4 GiB)
+
+Licenses (of 152065 crates):
+  MIT OR Apache-2.0    80123
+  MIT                  30456
+
+Editions:
+  2021    90123
+  2018    40456
+
+Rust versions:
+  1.70    5123
+  (none)  140456
+
+Categories (top 10):
+  command-line-utilities    8123
+
+Keywords (top 10):
+  cli    6123
+```
+*/
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "Crates: {} ({} yanked only, {} without a description)",
+            self.crates, self.yanked_only, self.missing_descriptions,
+        )?;
+        writeln!(
+            f,
+            "Versions: {} ({} yanked)",
+            self.versions, self.yanked_versions,
+        )?;
+        writeln!(
+            f,
+            "Crate files: {} ({})",
+            self.crate_files,
+            size(self.crate_file_size),
+        )?;
+
+        let sections = [
+            (
+                format!("Licenses (of {} crates)", self.manifests),
+                &self.licenses,
+            ),
+            (String::from("Editions"), &self.editions),
+            (String::from("Rust versions"), &self.rust_versions),
+            (
+                format!("Categories (top {})", self.categories.len()),
+                &self.categories,
+            ),
+            (
+                format!("Keywords (top {})", self.keywords.len()),
+                &self.keywords,
+            ),
+        ];
+        for (title, tallies) in sections {
+            if tallies.is_empty() {
+                continue;
+            }
+            let value = |x: &Tally| x.value.clone().unwrap_or_else(|| String::from("(none)"));
+            let width = tallies.iter().map(|x| value(x).len()).max().unwrap();
+            writeln!(f, "\n{title}:")?;
+            for x in tallies {
+                writeln!(f, "  {:<width$}    {}", value(x), x.count)?;
+            }
+        }
+
+        Ok(())
+    }
+}
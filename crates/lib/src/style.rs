@@ -0,0 +1,27 @@
+use std::{
+    env::var_os,
+    io::{stdout, IsTerminal},
+};
+
+pub use owo_colors::Style;
+
+/// Default style for highlighting matches in search results: bold green
+pub fn highlight_style() -> Style {
+    Style::new().green().bold()
+}
+
+/**
+Whether to color output when the color choice is automatic
+
+`CLICOLOR_FORCE` (other than `0`) forces color, `NO_COLOR` (non-empty) disables it, and otherwise
+color is used only if stdout is a terminal.
+*/
+pub fn auto_color() -> bool {
+    if var_os("CLICOLOR_FORCE").is_some_and(|x| !x.is_empty() && x != "0") {
+        true
+    } else if var_os("NO_COLOR").is_some_and(|x| !x.is_empty()) {
+        false
+    } else {
+        stdout().is_terminal()
+    }
+}
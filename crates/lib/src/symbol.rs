@@ -0,0 +1,322 @@
+use {
+    crate::{
+        error::Error,
+        highlight::{match_ranges, render, Highlighter},
+        krate::Crate,
+    },
+    anyhow::{anyhow, Result},
+    rayon::prelude::*,
+    regex::{Regex, RegexBuilder},
+    serde::{Deserialize, Serialize},
+    serde_json::json,
+    std::{
+        collections::{BTreeMap, HashSet},
+        fmt,
+        path::Path,
+        str::FromStr,
+    },
+    syn::{Item, Visibility},
+};
+
+/// Kind of a public item
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SymbolKind {
+    Fn,
+    Struct,
+    Trait,
+    Enum,
+}
+
+impl fmt::Display for SymbolKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            SymbolKind::Fn => "fn",
+            SymbolKind::Struct => "struct",
+            SymbolKind::Trait => "trait",
+            SymbolKind::Enum => "enum",
+        })
+    }
+}
+
+impl FromStr for SymbolKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<SymbolKind> {
+        match s {
+            "fn" => Ok(SymbolKind::Fn),
+            "struct" => Ok(SymbolKind::Struct),
+            "trait" => Ok(SymbolKind::Trait),
+            "enum" => Ok(SymbolKind::Enum),
+            _ => Err(anyhow!("Invalid symbol kind {s:?}")),
+        }
+    }
+}
+
+/**
+Public item exported by a crate
+
+Serialized to the cache file as a single string like `"struct uuid::Uuid"` to keep it compact.
+*/
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
+#[serde(into = "String", try_from = "String")]
+pub struct Symbol {
+    pub kind: SymbolKind,
+    pub path: String,
+}
+
+impl Symbol {
+    /// Item name without the module path
+    pub fn name(&self) -> &str {
+        self.path.rsplit("::").next().unwrap()
+    }
+}
+
+impl fmt::Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}", self.kind, self.path)
+    }
+}
+
+impl From<Symbol> for String {
+    fn from(symbol: Symbol) -> String {
+        symbol.to_string()
+    }
+}
+
+impl TryFrom<String> for Symbol {
+    type Error = anyhow::Error;
+
+    fn try_from(s: String) -> Result<Symbol> {
+        match s.split_once(' ') {
+            Some((kind, path)) => Ok(Symbol {
+                kind: kind.parse()?,
+                path: path.to_string(),
+            }),
+            None => Err(anyhow!("Invalid symbol {s:?}")),
+        }
+    }
+}
+
+/**
+Convert the path of a `.rs` file inside a crate's `src` directory to its module path
+
+Returns `None` for binary targets (`main.rs`, `bin/`), which do not export anything.
+
+```text
+lib.rs         => []
+foo.rs         => ["foo"]
+foo/mod.rs     => ["foo"]
+foo/bar.rs     => ["foo", "bar"]
+```
+*/
+pub fn module_path(src_path: &Path) -> Option<Vec<String>> {
+    let mut r = src_path
+        .iter()
+        .map(|x| x.to_str().map(String::from))
+        .collect::<Option<Vec<_>>>()?;
+
+    if r.first().map(String::as_str) == Some("bin") {
+        return None;
+    }
+
+    let file = r.pop()?;
+    match file.as_str() {
+        "main.rs" if r.is_empty() => return None,
+        "lib.rs" if r.is_empty() => {}
+        "mod.rs" => {}
+        _ => r.push(file.strip_suffix(".rs")?.to_string()),
+    }
+
+    Some(r)
+}
+
+/// Extract the public functions, structs, traits, and enums defined in a source file
+pub fn extract_symbols(prefix: &[String], content: &str) -> Result<Vec<Symbol>> {
+    let file = syn::parse_file(content)?;
+    let mut r = vec![];
+    extract_items(prefix, &file.items, &mut r);
+    Ok(r)
+}
+
+fn extract_items(prefix: &[String], items: &[Item], symbols: &mut Vec<Symbol>) {
+    for item in items {
+        let (kind, vis, ident) = match item {
+            Item::Fn(i) => (SymbolKind::Fn, &i.vis, &i.sig.ident),
+            Item::Struct(i) => (SymbolKind::Struct, &i.vis, &i.ident),
+            Item::Trait(i) => (SymbolKind::Trait, &i.vis, &i.ident),
+            Item::Enum(i) => (SymbolKind::Enum, &i.vis, &i.ident),
+            Item::Mod(i) => {
+                // Descend into inline public modules
+                if let (Visibility::Public(_), Some((_, items))) = (&i.vis, &i.content) {
+                    let mut prefix = prefix.to_vec();
+                    prefix.push(i.ident.to_string());
+                    extract_items(&prefix, items, symbols);
+                }
+                continue;
+            }
+            _ => continue,
+        };
+
+        if matches!(vis, Visibility::Public(_)) {
+            let mut path = prefix.to_vec();
+            path.push(ident.to_string());
+            symbols.push(Symbol {
+                kind,
+                path: path.join("::"),
+            });
+        }
+    }
+}
+
+pub struct SymbolSearch<'a> {
+    pub name_exact: Vec<(&'a Crate, &'a Symbol)>,
+    pub name_contains: Vec<(&'a Crate, &'a Symbol)>,
+    re: Vec<Regex>,
+}
+
+impl<'a> SymbolSearch<'a> {
+    /**
+    Search the public items of the crates, including same-named crates from other registries
+
+    Fails if a query is not a valid regular expression.
+    */
+    pub fn new(
+        queries: &[String],
+        case_insensitive: bool,
+        crates: &'a BTreeMap<String, Crate>,
+    ) -> Result<SymbolSearch<'a>, Error> {
+        let re = queries
+            .iter()
+            .map(|x| {
+                RegexBuilder::new(x)
+                    .case_insensitive(case_insensitive)
+                    .build()
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let symbols = crates
+            .par_iter()
+            .flat_map_iter(|(_name, crate_)| std::iter::once(crate_).chain(&crate_.conflicts))
+            .flat_map_iter(|crate_| crate_.symbols.iter().map(move |x| (crate_, x)))
+            .collect::<Vec<_>>();
+
+        // Exact item name matches
+        let name_exact = symbols
+            .par_iter()
+            .filter(|(_crate_, symbol)| {
+                queries.iter().any(|query| {
+                    if case_insensitive {
+                        symbol.name().eq_ignore_ascii_case(query)
+                    } else {
+                        symbol.name() == query
+                    }
+                })
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+
+        // Item names matching any query, excluding exact matches
+        let exact = name_exact
+            .iter()
+            .map(|(crate_, symbol)| (&crate_.name, &crate_.registry, &symbol.path))
+            .collect::<HashSet<_>>();
+        let name_contains = symbols
+            .into_par_iter()
+            .filter(|(crate_, symbol)| {
+                !exact.contains(&(&crate_.name, &crate_.registry, &symbol.path))
+                    && re.iter().any(|re| re.is_match(symbol.name()))
+            })
+            .collect::<Vec<_>>();
+
+        Ok(SymbolSearch {
+            name_exact,
+            name_contains,
+            re,
+        })
+    }
+
+    /// Format like `cargo search` with the matching items, highlighting matches with the
+    /// highlighter if any
+    pub fn to_string(&self, include_yanked: bool, highlighter: Option<&dyn Highlighter>) -> String {
+        let mut width = 0;
+        let mut lines = vec![];
+
+        for (crate_, symbol) in self.name_exact.iter().chain(self.name_contains.iter()) {
+            let name_and_version = format!(
+                "{} = \"{}\"    ",
+                crate_.name,
+                crate_.version(include_yanked)
+            );
+
+            width = width.max(name_and_version.len());
+
+            let tag = crate_
+                .registry
+                .as_ref()
+                .map(|x| format!("[{x}] "))
+                .unwrap_or_default();
+
+            lines.push((name_and_version, tag, symbol));
+        }
+
+        lines
+            .iter()
+            .map(|(name_and_version, tag, symbol)| {
+                let s = " ".repeat(width - name_and_version.len());
+                if let Some(highlighter) = highlighter {
+                    format!(
+                        "{}{s}# {}{} {}\n",
+                        highlighter.text(name_and_version),
+                        highlighter.text(tag),
+                        symbol.kind,
+                        self.highlight(&symbol.path, highlighter),
+                    )
+                } else {
+                    format!("{name_and_version}{s}# {tag}{symbol}\n")
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("")
+    }
+
+    fn highlight(&self, path: &str, highlighter: &dyn Highlighter) -> String {
+        // Only highlight the item name, not the module path
+        let (module, name) = match path.rsplit_once("::") {
+            Some((module, name)) => (format!("{module}::"), name),
+            None => (String::new(), path),
+        };
+        format!(
+            "{}{}",
+            highlighter.text(&module),
+            render(name, &match_ranges(&self.re, name), highlighter),
+        )
+    }
+
+    /// Results as a JSON array of objects with name, version, registry, and symbol
+    pub fn to_json(&self, include_yanked: bool) -> Result<String> {
+        let results = self
+            .name_exact
+            .iter()
+            .chain(self.name_contains.iter())
+            .map(|(crate_, symbol)| {
+                json!({
+                    "name": crate_.name,
+                    "version": crate_.version(include_yanked).to_string(),
+                    "registry": crate_.registry,
+                    "symbol": symbol,
+                })
+            })
+            .collect::<Vec<_>>();
+        Ok(serde_json::to_string_pretty(&results)?)
+    }
+
+    /// Keep only the first `limit` results, exact item name matches first
+    pub fn truncate(&mut self, limit: usize) {
+        self.name_exact.truncate(limit);
+        self.name_contains.truncate(limit - self.name_exact.len());
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.name_exact.is_empty() && self.name_contains.is_empty()
+    }
+}
@@ -0,0 +1,339 @@
+/*!
+Synthetic panamax mirrors for tests
+
+[`mini_mirror`] builds a tiny mirror with one crate per quirk seen in real mirrors.
+*/
+
+#![allow(dead_code)]
+
+use {
+    flate2::{write::GzEncoder, Compression},
+    std::{
+        fs::{create_dir_all, rename, write, OpenOptions},
+        io::Write,
+        path::{Path, PathBuf},
+        process::Command,
+    },
+    tempfile::TempDir,
+};
+
+/// Panamax mirror in a temporary directory, deleted when dropped
+pub struct Mirror {
+    dir: TempDir,
+}
+
+impl Mirror {
+    /// Empty mirror with a git index and panamax's `config.json`
+    pub fn new() -> Mirror {
+        let dir = TempDir::new().unwrap();
+        let index = dir.path().join("crates.io-index");
+        create_dir_all(&index).unwrap();
+        write(
+            index.join("config.json"),
+            r#"{"dl":"http://localhost/crates","api":"http://localhost"}"#,
+        )
+        .unwrap();
+        Mirror { dir }
+    }
+
+    pub fn path(&self) -> &Path {
+        self.dir.path()
+    }
+
+    pub fn config_file(&self) -> PathBuf {
+        self.path().join("crates.io-index").join("config.json")
+    }
+
+    pub fn cache_file(&self) -> PathBuf {
+        self.path().join("search.json")
+    }
+
+    /// Path of a crate's index file, like `crates.io-index/se/rd/serde`
+    pub fn index_file(&self, name: &str) -> PathBuf {
+        let name = name.to_lowercase();
+        let prefix = match name.len() {
+            1 => String::from("1"),
+            2 => String::from("2"),
+            3 => format!("3/{}", &name[..1]),
+            _ => format!("{}/{}", &name[..2], &name[2..4]),
+        };
+        self.path().join("crates.io-index").join(prefix).join(name)
+    }
+
+    /// Run `git` in the index directory and return its output
+    pub fn git(&self, args: &[&str]) -> String {
+        let output = Command::new("git")
+            .args(["-c", "user.name=test", "-c", "user.email=test@localhost"])
+            .args(args)
+            .current_dir(self.path().join("crates.io-index"))
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{output:?}");
+        String::from_utf8(output.stdout).unwrap().trim().to_string()
+    }
+
+    /// Commit all changes to the index as a git repository, like a sync, and return the commit
+    pub fn git_commit(&self) -> String {
+        self.git(&["init", "--quiet"]);
+        self.git(&["add", "--all"]);
+        self.git(&["commit", "--quiet", "--allow-empty", "--message", "Sync"]);
+        self.git(&["rev-parse", "HEAD"])
+    }
+
+    /// Append a line to a crate's index file
+    pub fn add_index_line(&self, name: &str, line: &str) {
+        let index_file = self.index_file(name);
+        create_dir_all(index_file.parent().unwrap()).unwrap();
+        let mut f = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(index_file)
+            .unwrap();
+        writeln!(f, "{line}").unwrap();
+    }
+
+    pub fn add_version(&self, name: &str, version: &str, yanked: bool) {
+        self.add_index_line(name, &index_line(name, version, yanked));
+    }
+
+    /**
+    Move the index from `crates.io-index` to `index` like a sparse-protocol mirror, optionally also
+    moving `config.json` to the mirror directory
+
+    Call after adding crates, since the other methods use the git index's paths.
+    */
+    pub fn make_sparse(&self, config_at_root: bool) {
+        let index = self.path().join("index");
+        rename(self.path().join("crates.io-index"), &index).unwrap();
+        if config_at_root {
+            rename(index.join("config.json"), self.path().join("config.json")).unwrap();
+        }
+    }
+
+    /**
+    Path of a crate file in panamax's layout
+
+    ```text
+    a    => crates/1/a/a/0.1.0/a-0.1.0.crate
+    ab   => crates/2/ab/ab/0.1.0/ab-0.1.0.crate
+    abc  => crates/3/a/abc/0.1.0/abc-0.1.0.crate
+    abcd => crates/ab/cd/abcd/0.1.0/abcd-0.1.0.crate
+    ```
+    */
+    pub fn crate_file(&self, name: &str, version: &str) -> PathBuf {
+        let crates = self.path().join("crates");
+        match name.len() {
+            1 => crates.join("1").join(&name[..1]),
+            2 => crates.join("2").join(&name[..2]),
+            3 => crates.join("3").join(&name[..1]),
+            _ => crates.join(&name[..2]).join(&name[2..4]),
+        }
+        .join(name)
+        .join(version)
+        .join(format!("{name}-{version}.crate"))
+    }
+
+    /// Replace the `dl` download URL template in `config.json`
+    pub fn set_dl(&self, dl: &str) {
+        write(
+            self.config_file(),
+            format!(r#"{{"dl":"{dl}","api":"http://localhost"}}"#),
+        )
+        .unwrap();
+    }
+
+    /// Add a version with a crate file at a path relative to the mirror directory, e.g. in a
+    /// layout given by a `dl` template
+    pub fn add_crate_at(&self, path: &str, name: &str, version: &str, description: &str) {
+        self.add_version(name, version, false);
+        let path = self.path().join(path);
+        create_dir_all(path.parent().unwrap()).unwrap();
+        let cargo_toml = cargo_toml("package", name, version, Some(description));
+        write(
+            path,
+            crate_file(name, version, &[("Cargo.toml", &cargo_toml)]),
+        )
+        .unwrap();
+    }
+
+    /// Write a crate file with files relative to the `{name}-{version}` directory
+    pub fn add_crate_file(&self, name: &str, version: &str, files: &[(&str, &str)]) {
+        self.add_raw_crate_file(name, version, &crate_file(name, version, files));
+    }
+
+    /// Write a crate file with arbitrary contents, e.g. a corrupt one
+    pub fn add_raw_crate_file(&self, name: &str, version: &str, data: &[u8]) {
+        let crate_file = self.crate_file(name, version);
+        create_dir_all(crate_file.parent().unwrap()).unwrap();
+        write(crate_file, data).unwrap();
+    }
+
+    /// Add a version with a crate file containing just a `Cargo.toml` with a `[package]` section
+    pub fn add_crate(&self, name: &str, version: &str, description: Option<&str>) {
+        self.add_version(name, version, false);
+        self.add_crate_file(
+            name,
+            version,
+            &[(
+                "Cargo.toml",
+                &cargo_toml("package", name, version, description),
+            )],
+        );
+    }
+}
+
+/// Contents of a crate file with files relative to the `{name}-{version}` directory
+pub fn crate_file(name: &str, version: &str, files: &[(&str, &str)]) -> Vec<u8> {
+    let mut builder = tar::Builder::new(GzEncoder::new(vec![], Compression::default()));
+    for (path, content) in files {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(content.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder
+            .append_data(
+                &mut header,
+                format!("{name}-{version}/{path}"),
+                content.as_bytes(),
+            )
+            .unwrap();
+    }
+    builder.into_inner().unwrap().finish().unwrap()
+}
+
+/// Index file line of a version
+pub fn index_line(name: &str, version: &str, yanked: bool) -> String {
+    format!(r#"{{"name":"{name}","vers":"{version}","deps":[],"features":{{}},"yanked":{yanked}}}"#)
+}
+
+/**
+Contents of a cargo `.cache` index file with the given versions
+
+```text
+[cache version: u8 = 3][index format version: u32 LE][index file version]\0
+[version]\0[JSON]\0
+...
+```
+*/
+pub fn cargo_cache_file(name: &str, versions: &[(&str, bool)]) -> Vec<u8> {
+    let mut r = vec![3];
+    r.extend(2u32.to_le_bytes());
+    r.extend(b"etag\0");
+    for (version, yanked) in versions {
+        r.extend(version.as_bytes());
+        r.push(0);
+        r.extend(index_line(name, version, *yanked).as_bytes());
+        r.push(0);
+    }
+    r
+}
+
+/// Minimal `Cargo.toml` with a `[package]` or `[project]` section
+pub fn cargo_toml(section: &str, name: &str, version: &str, description: Option<&str>) -> String {
+    let mut r = format!("[{section}]\nname = \"{name}\"\nversion = \"{version}\"\n");
+    if let Some(description) = description {
+        r.push_str(&format!("description = \"{description}\"\n"));
+    }
+    r
+}
+
+/**
+Mirror with one crate per quirk
+
+Crate | Quirk | Description
+---|---|---
+`a` | One-letter name, license, and README | Single letter crate
+`ab` | `cargo.toml` instead of `Cargo.toml` | Lowercase manifest
+`abc` | `[project]` instead of `[package]` | Project section
+`abcd` | Two versions | Four letters
+`no-description` | No `description` | -
+`corrupt` | Corrupt `.crate` file | -
+`empty` | Zero entries in `.crate` file | -
+`malformed` | Malformed `Cargo.toml` | -
+`missing` | No `.crate` file | -
+`yanked` | Latest version yanked | Latest version yanked
+`all-yanked` | All versions yanked | All versions yanked
+*/
+pub fn mini_mirror() -> Mirror {
+    let mirror = Mirror::new();
+
+    mirror.add_version("a", "0.1.0", false);
+    mirror.add_crate_file(
+        "a",
+        "0.1.0",
+        &[
+            (
+                "Cargo.toml",
+                &format!(
+                    "{}license = \"MIT\"\n",
+                    cargo_toml("package", "a", "0.1.0", Some("Single letter crate"))
+                ),
+            ),
+            ("README.md", "# a\n"),
+            (
+                "src/lib.rs",
+                "pub struct A;\npub fn a() {}\nfn private() {}\n",
+            ),
+        ],
+    );
+
+    mirror.add_version("ab", "0.1.0", false);
+    mirror.add_crate_file(
+        "ab",
+        "0.1.0",
+        &[(
+            "cargo.toml",
+            &cargo_toml("package", "ab", "0.1.0", Some("Lowercase manifest")),
+        )],
+    );
+
+    mirror.add_version("abc", "0.1.0", false);
+    mirror.add_crate_file(
+        "abc",
+        "0.1.0",
+        &[(
+            "Cargo.toml",
+            &cargo_toml("project", "abc", "0.1.0", Some("Project section")),
+        )],
+    );
+
+    mirror.add_crate("abcd", "0.1.0", Some("Old description"));
+    mirror.add_crate("abcd", "0.2.0", Some("Four letters"));
+
+    mirror.add_crate("no-description", "1.0.0", None);
+
+    mirror.add_version("corrupt", "1.0.0", false);
+    mirror.add_raw_crate_file("corrupt", "1.0.0", b"not a gzipped tarball");
+
+    mirror.add_version("empty", "1.0.0", false);
+    mirror.add_crate_file("empty", "1.0.0", &[]);
+
+    mirror.add_version("malformed", "1.0.0", false);
+    mirror.add_crate_file(
+        "malformed",
+        "1.0.0",
+        &[("Cargo.toml", "[package\nname = \"malformed\"\n")],
+    );
+
+    mirror.add_version("missing", "1.0.0", false);
+
+    mirror.add_crate("yanked", "1.0.0", Some("Latest version yanked"));
+    mirror.add_version("yanked", "1.1.0", true);
+
+    mirror.add_version("all-yanked", "0.1.0", true);
+    mirror.add_crate_file(
+        "all-yanked",
+        "0.1.0",
+        &[(
+            "Cargo.toml",
+            &cargo_toml(
+                "package",
+                "all-yanked",
+                "0.1.0",
+                Some("All versions yanked"),
+            ),
+        )],
+    );
+
+    mirror
+}
@@ -0,0 +1,820 @@
+mod fixture;
+
+use {
+    fixture::{cargo_cache_file, cargo_toml, crate_file, mini_mirror, Mirror},
+    flate2::{write::GzEncoder, Compression},
+    panamax_search_lib::{
+        Crate, DescriptionStatus, Error, Index, MemorySource, Phase, Progress, Tally, TarballSource,
+    },
+    semver::Version,
+    std::{
+        fs::{create_dir_all, write, File},
+        sync::{Arc, Mutex},
+        time::{Duration, SystemTime},
+    },
+    tempfile::NamedTempFile,
+};
+
+fn description(index: &Index, name: &str) -> Option<String> {
+    index.get(name).unwrap().description().map(String::from)
+}
+
+#[test]
+fn rebuild_reads_descriptions_for_each_quirk() {
+    let mirror = mini_mirror();
+    let index = Index::load_from_mirror_directory(mirror.path()).unwrap();
+
+    assert_eq!(index.len(), 11);
+    assert_eq!(
+        description(&index, "a").as_deref(),
+        Some("Single letter crate")
+    );
+    assert_eq!(
+        description(&index, "ab").as_deref(),
+        Some("Lowercase manifest")
+    );
+    assert_eq!(
+        description(&index, "abc").as_deref(),
+        Some("Project section")
+    );
+    assert_eq!(description(&index, "abcd").as_deref(), Some("Four letters"));
+    assert_eq!(
+        description(&index, "yanked").as_deref(),
+        Some("Latest version yanked")
+    );
+    assert_eq!(
+        description(&index, "all-yanked").as_deref(),
+        Some("All versions yanked")
+    );
+    for name in ["no-description", "corrupt", "empty", "malformed", "missing"] {
+        assert_eq!(description(&index, name), None, "{name}");
+    }
+}
+
+#[test]
+fn description_status_for_each_quirk() {
+    let mirror = mini_mirror();
+    Index::load(mirror.path()).unwrap();
+    // Statuses are kept in the cache file
+    let index = Index::load_from_cache_file(mirror.path()).unwrap();
+
+    for (name, status) in [
+        ("a", DescriptionStatus::Ok),
+        ("ab", DescriptionStatus::LowercaseManifest),
+        ("abc", DescriptionStatus::ProjectSection),
+        ("no-description", DescriptionStatus::NoDescription),
+        ("corrupt", DescriptionStatus::CorruptCrateFile),
+        ("empty", DescriptionStatus::EmptyCrateFile),
+        ("malformed", DescriptionStatus::MalformedManifest),
+        ("missing", DescriptionStatus::MissingCrateFile),
+    ] {
+        assert_eq!(
+            index.get(name).unwrap().description_status(),
+            status,
+            "{name}"
+        );
+    }
+
+    let report = index.description_report();
+    assert_eq!(report.crates, 11);
+    assert_eq!(report.descriptions, 6);
+    assert_eq!(report.count(DescriptionStatus::Ok), 4);
+    assert_eq!(
+        report.statuses[&DescriptionStatus::NoDescription][0].name,
+        "no-description"
+    );
+}
+
+#[test]
+fn rebuild_reads_versions() {
+    let mirror = mini_mirror();
+    let index = Index::load_from_mirror_directory(mirror.path()).unwrap();
+
+    let abcd = index.get("abcd").unwrap();
+    assert_eq!(abcd.versions, 2);
+    assert_eq!(abcd.version(false), Version::new(0, 2, 0));
+
+    let yanked = index.get("yanked").unwrap();
+    assert_eq!(yanked.version(false), Version::new(1, 0, 0));
+    assert_eq!(yanked.version(true), Version::new(1, 1, 0));
+    assert_eq!(yanked.yanked_versions(), 1);
+
+    let all_yanked = index.get("all-yanked").unwrap();
+    assert_eq!(all_yanked.latest_non_yanked(), None);
+    assert_eq!(all_yanked.version(false), Version::new(0, 0, 0));
+    assert_eq!(all_yanked.version(true), Version::new(0, 1, 0));
+}
+
+#[test]
+fn rebuild_skips_invalid_index_files() {
+    let mirror = mini_mirror();
+    mirror.add_index_line("invalid", "not json");
+    let index = Index::load_from_mirror_directory(mirror.path()).unwrap();
+
+    assert!(!index.contains("invalid"));
+    assert_eq!(index.len(), 11);
+}
+
+#[test]
+fn invalid_index_line_has_line_number() {
+    let mirror = Mirror::new();
+    mirror.add_version("invalid", "0.1.0", false);
+    mirror.add_version("invalid", "0.2.0", true);
+    mirror.add_index_line("invalid", "not json");
+
+    match Crate::new(&mirror.index_file("invalid")) {
+        Err(Error::InvalidIndexLine { line, .. }) => assert_eq!(line, 3),
+        r => panic!("{r:?}"),
+    }
+}
+
+#[test]
+fn invalid_old_index_line_is_skipped() {
+    let mirror = Mirror::new();
+    mirror.add_version("invalid", "0.1.0", true);
+    mirror.add_index_line("invalid", "not json");
+    mirror.add_index_line("invalid", "");
+    mirror.add_index_line(
+        "invalid",
+        r#"{"name":"invalid", "vers":"0.1.1", "deps":[], "features":{}, "yanked": true}"#,
+    );
+    mirror.add_index_line(
+        "invalid",
+        r#"{"name":"invalid","vers":"0.1.2","deps":[],"features":{"\"yanked\":true":[]},"yanked":false}"#,
+    );
+    mirror.add_version("invalid", "0.2.0", false);
+    mirror.add_version("invalid", "0.3.0", true);
+
+    // Blank and invalid lines aren't versions, and yanked versions are counted by their field
+    let crate_ = Crate::new(&mirror.index_file("invalid")).unwrap();
+    assert_eq!(crate_.latest_non_yanked(), Some(&Version::new(0, 2, 0)));
+    assert_eq!(crate_.latest_yanked(), Some(&Version::new(0, 3, 0)));
+    assert_eq!(crate_.versions, 5);
+    assert_eq!(crate_.yanked_versions(), 3);
+}
+
+#[test]
+fn empty_index_file() {
+    let mirror = Mirror::new();
+    mirror.add_version("empty", "0.1.0", false);
+    write(mirror.index_file("empty"), "").unwrap();
+
+    assert!(matches!(
+        Crate::new(&mirror.index_file("empty")),
+        Err(Error::EmptyIndexFile(_))
+    ));
+}
+
+#[test]
+fn cargo_cache_files() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let parse = |data: &[u8]| {
+        let index_file = dir.path().join("foo");
+        write(&index_file, data).unwrap();
+        Crate::new(&index_file)
+    };
+
+    let valid = cargo_cache_file(
+        "foo",
+        &[("0.1.0", false), ("0.2.0", false), ("0.3.0", true)],
+    );
+    let crate_ = parse(&valid).unwrap();
+    assert_eq!(crate_.name(), "foo");
+    assert_eq!(crate_.latest_non_yanked(), Some(&Version::new(0, 2, 0)));
+    assert_eq!(crate_.latest_yanked(), Some(&Version::new(0, 3, 0)));
+    assert_eq!(crate_.versions, 3);
+    assert_eq!(crate_.yanked_versions(), 1);
+
+    // Truncated in the header or in the last entry
+    assert!(matches!(
+        parse(&valid[..3]),
+        Err(Error::InvalidCargoCacheFile { .. })
+    ));
+    assert!(matches!(
+        parse(&valid[..valid.len() - 10]),
+        Err(Error::InvalidIndexLine { line: 3, .. })
+    ));
+
+    // No entries
+    assert!(matches!(
+        parse(&cargo_cache_file("foo", &[])),
+        Err(Error::NoVersions(_))
+    ));
+    assert!(matches!(parse(b""), Err(Error::EmptyIndexFile(_))));
+
+    // Unsupported cache file version
+    let mut wrong_version = valid.clone();
+    wrong_version[0] = 1;
+    assert!(matches!(
+        parse(&wrong_version),
+        Err(Error::InvalidCargoCacheFile { .. })
+    ));
+}
+
+#[test]
+fn sparse_layout() {
+    for config_at_root in [false, true] {
+        let mirror = mini_mirror();
+        mirror.make_sparse(config_at_root);
+
+        let index = Index::load(mirror.path()).unwrap();
+        assert_eq!(index.len(), 11);
+        assert_eq!(index.head(), None);
+        assert_eq!(
+            description(&index, "a").as_deref(),
+            Some("Single letter crate")
+        );
+        assert_eq!(description(&index, "abcd").as_deref(), Some("Four letters"));
+        Index::load_from_cache_file(mirror.path()).unwrap();
+
+        // Freshness is decided by the modification time of `config.json`
+        let config_file = if config_at_root {
+            mirror.path().join("config.json")
+        } else {
+            mirror.path().join("index").join("config.json")
+        };
+        File::options()
+            .write(true)
+            .open(config_file)
+            .unwrap()
+            .set_modified(SystemTime::now() + Duration::from_secs(3600))
+            .unwrap();
+        assert!(matches!(
+            Index::load_from_cache_file(mirror.path()),
+            Err(Error::CacheFileStale(_))
+        ));
+    }
+}
+
+#[test]
+fn cargo_home() {
+    let cargo_home = tempfile::TempDir::new().unwrap();
+    let registry = cargo_home.path().join("registry");
+    let add_registry =
+        |name: &str, crates: &[(&str, &[(&str, bool)])], downloaded: &[(&str, &str)]| {
+            let index = registry.join("index").join(name);
+            create_dir_all(index.join(".cache/3/f")).unwrap();
+            write(
+                index.join("config.json"),
+                r#"{"dl":"https://static.crates.io/crates"}"#,
+            )
+            .unwrap();
+            for (crate_name, versions) in crates {
+                write(
+                    index.join(".cache/3/f").join(crate_name),
+                    cargo_cache_file(crate_name, versions),
+                )
+                .unwrap();
+            }
+            let cache = registry.join("cache").join(name);
+            create_dir_all(&cache).unwrap();
+            for (crate_name, version) in downloaded {
+                let cargo_toml = cargo_toml("package", crate_name, version, Some(name));
+                write(
+                    cache.join(format!("{crate_name}-{version}.crate")),
+                    crate_file(crate_name, version, &[("Cargo.toml", &cargo_toml)]),
+                )
+                .unwrap();
+            }
+        };
+    add_registry(
+        "index.crates.io-1949cf8c6b5b557f",
+        &[
+            ("foo", &[("0.1.0", false), ("0.2.0", false)]),
+            ("fum", &[("1.0.0", false)]),
+        ],
+        &[("foo", "0.1.0")],
+    );
+    add_registry(
+        "internal-0123456789abcdef",
+        &[("foo", &[("0.3.0", false)])],
+        &[("foo", "0.3.0")],
+    );
+
+    let registries = Index::cargo_registries(cargo_home.path()).unwrap();
+    assert_eq!(
+        registries.iter().map(|x| x.0.as_str()).collect::<Vec<_>>(),
+        ["index.crates.io", "internal"]
+    );
+
+    // Only downloaded crates, described from the latest downloaded version
+    let index = Index::load_from_cargo_home(cargo_home.path()).unwrap();
+    assert_eq!(index.names().collect::<Vec<_>>(), ["foo"]);
+    let foo = index.get("foo").unwrap();
+    assert_eq!(foo.registry(), Some("index.crates.io"));
+    assert_eq!(foo.description(), Some("index.crates.io-1949cf8c6b5b557f"));
+    assert_eq!(foo.conflicts().len(), 1);
+    assert_eq!(foo.conflicts()[0].registry(), Some("internal"));
+    assert_eq!(
+        foo.conflicts()[0].latest_non_yanked(),
+        Some(&Version::new(0, 3, 0))
+    );
+
+    // A newly downloaded crate makes the cache file stale
+    let index = registry
+        .join("index")
+        .join("index.crates.io-1949cf8c6b5b557f");
+    Index::load_from_cache_file(&index).unwrap();
+    let cache = registry
+        .join("cache")
+        .join("index.crates.io-1949cf8c6b5b557f");
+    write(
+        cache.join("fum-1.0.0.crate"),
+        crate_file("fum", "1.0.0", &[]),
+    )
+    .unwrap();
+    File::open(&cache)
+        .unwrap()
+        .set_modified(SystemTime::now() + Duration::from_secs(3600))
+        .unwrap();
+    assert!(matches!(
+        Index::load_from_cache_file(&index),
+        Err(Error::CacheFileStale(_))
+    ));
+    assert!(Index::load(&index).unwrap().contains("fum"));
+}
+
+#[test]
+fn dl_template_layouts() {
+    // Markers, with 1-, 2-, 3-, and 4+-character names
+    let mirror = Mirror::new();
+    mirror.set_dl("http://localhost/files/{prefix}/{crate}/{crate}-{version}.crate");
+    for (path, name) in [
+        ("files/1/a/a-0.1.0.crate", "a"),
+        ("files/2/ab/ab-0.1.0.crate", "ab"),
+        ("files/3/a/abc/abc-0.1.0.crate", "abc"),
+        ("files/ab/cd/abcd/abcd-0.1.0.crate", "abcd"),
+        ("files/My/Cr/MyCrate/MyCrate-0.1.0.crate", "MyCrate"),
+    ] {
+        mirror.add_crate_at(path, name, "0.1.0", name);
+    }
+    let index = Index::load(mirror.path()).unwrap();
+    for name in ["a", "ab", "abc", "abcd", "MyCrate"] {
+        assert_eq!(description(&index, name).as_deref(), Some(name));
+    }
+
+    // `{lowerprefix}`
+    let mirror = Mirror::new();
+    mirror.set_dl("http://localhost/{lowerprefix}/{crate}-{version}.crate");
+    mirror.add_crate_at("my/cr/MyCrate-0.1.0.crate", "MyCrate", "0.1.0", "Lower");
+    let index = Index::load(mirror.path()).unwrap();
+    assert_eq!(description(&index, "MyCrate").as_deref(), Some("Lower"));
+
+    // No markers, like crates.io's API: `/{crate}/{version}/download` is appended
+    let mirror = Mirror::new();
+    mirror.set_dl("http://localhost/api/v1/crates");
+    mirror.add_crate_at("api/v1/crates/abcd/0.1.0/download", "abcd", "0.1.0", "API");
+    let index = Index::load(mirror.path()).unwrap();
+    assert_eq!(description(&index, "abcd").as_deref(), Some("API"));
+
+    // An absolute `file://` URL
+    let mirror = Mirror::new();
+    mirror.set_dl(&format!(
+        "file://{}/store/{{crate}}-{{version}}.crate",
+        mirror.path().display()
+    ));
+    mirror.add_crate_at("store/abc-0.1.0.crate", "abc", "0.1.0", "File");
+    let index = Index::load(mirror.path()).unwrap();
+    assert_eq!(description(&index, "abc").as_deref(), Some("File"));
+
+    // Falls back to panamax's layout if the template doesn't match the mirror directory
+    let mirror = mini_mirror();
+    mirror.set_dl("http://localhost/{crate}/{version}/{sha256-checksum}");
+    let index = Index::load(mirror.path()).unwrap();
+    assert_eq!(description(&index, "abcd").as_deref(), Some("Four letters"));
+    mirror.set_dl("http://localhost/elsewhere/{crate}-{version}.crate");
+    let index = Index::load_from_mirror_directory(mirror.path()).unwrap();
+    assert_eq!(description(&index, "abcd").as_deref(), Some("Four letters"));
+}
+
+#[test]
+fn details_from_crate_file() {
+    let mirror = mini_mirror();
+    let index = Index::load_from_mirror_directory(mirror.path()).unwrap();
+
+    let details = index.get("a").unwrap().details().unwrap();
+    assert_eq!(details.license.as_deref(), Some("MIT"));
+    assert_eq!(details.readme.as_deref(), Some("# a\n"));
+    assert_eq!(details.versions, [(Version::new(0, 1, 0), false)]);
+    assert_eq!(
+        details.to_string(),
+        "License: MIT\nVersions: 1 (0 yanked): 0.1.0\n\nDependencies of 0.1.0:\n",
+    );
+
+    // Missing crate files leave the license and README empty
+    let details = index.get("missing").unwrap().details().unwrap();
+    assert_eq!(details.license, None);
+    assert_eq!(details.readme, None);
+
+    assert!(matches!(
+        index
+            .get("a")
+            .unwrap()
+            .version_details(&Version::new(9, 0, 0)),
+        Err(Error::VersionNotFound { .. })
+    ));
+    assert!(matches!(
+        Crate::new(&mirror.index_file("a")).unwrap().details(),
+        Err(Error::UnknownMirror(_))
+    ));
+}
+
+#[test]
+fn non_string_license() {
+    let mirror = Mirror::new();
+    mirror.add_version("foo", "0.1.0", false);
+    mirror.add_crate_file(
+        "foo",
+        "0.1.0",
+        &[(
+            "Cargo.toml",
+            &format!(
+                "{}license.workspace = true\n",
+                cargo_toml("package", "foo", "0.1.0", Some("Foo"))
+            ),
+        )],
+    );
+    let index = Index::load_from_mirror_directory(mirror.path()).unwrap();
+
+    assert_eq!(description(&index, "foo").as_deref(), Some("Foo"));
+    assert_eq!(index.get("foo").unwrap().details().unwrap().license, None);
+}
+
+#[test]
+fn rebuild_with_symbols() {
+    let mirror = mini_mirror();
+    let index = Index::load_from_mirror_directory_with_symbols(mirror.path()).unwrap();
+
+    assert!(index.has_symbols());
+    let symbols = index
+        .get("a")
+        .unwrap()
+        .symbols()
+        .iter()
+        .map(|x| x.to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(symbols, ["fn a::a", "struct a::A"]);
+}
+
+#[test]
+fn load_saves_and_reads_cache_file() {
+    let mirror = mini_mirror();
+    assert!(matches!(
+        Index::load_from_cache_file(mirror.path()),
+        Err(Error::CacheFileNotFound(_))
+    ));
+
+    let built = Index::load(mirror.path()).unwrap();
+    assert!(mirror.cache_file().is_file());
+
+    let cached = Index::load_from_cache_file(mirror.path()).unwrap();
+    assert_eq!(
+        cached.names().collect::<Vec<_>>(),
+        built.names().collect::<Vec<_>>()
+    );
+    for crate_ in &built {
+        assert_eq!(
+            description(&cached, crate_.name()).as_deref(),
+            crate_.description(),
+            "{}",
+            crate_.name()
+        );
+    }
+
+    // Crates loaded from the cache file can still read their details
+    let details = cached.get("a").unwrap().details().unwrap();
+    assert_eq!(details.license.as_deref(), Some("MIT"));
+}
+
+#[test]
+fn stale_cache_file() {
+    let mirror = mini_mirror();
+    Index::load(mirror.path()).unwrap();
+
+    // Sync the mirror after the cache file was saved
+    File::options()
+        .write(true)
+        .open(mirror.cache_file())
+        .unwrap()
+        .set_modified(SystemTime::now() - Duration::from_secs(3600))
+        .unwrap();
+    assert!(matches!(
+        Index::load_from_cache_file(mirror.path()),
+        Err(Error::CacheFileStale(_))
+    ));
+
+    // Loading rebuilds the cache file and keeps the stale one as the snapshot
+    Index::load(mirror.path()).unwrap();
+    Index::load_from_cache_file(mirror.path()).unwrap();
+    assert!(mirror.path().join("search.prev.json").is_file());
+}
+
+#[test]
+fn stale_cache_file_from_git_head() {
+    let mirror = mini_mirror();
+    let first = mirror.git_commit();
+    let index = Index::load(mirror.path()).unwrap();
+    assert_eq!(index.head(), Some(first.as_str()));
+    assert_eq!(
+        Index::load_from_cache_file(mirror.path()).unwrap().head(),
+        Some(first.as_str())
+    );
+
+    // A sync that doesn't touch `config.json` is detected by the new HEAD
+    let second = mirror.git_commit();
+    assert!(matches!(
+        Index::load_from_cache_file(mirror.path()),
+        Err(Error::CacheFileStale(_))
+    ));
+    assert_eq!(
+        Index::load(mirror.path()).unwrap().head(),
+        Some(second.as_str())
+    );
+
+    // The HEAD decides freshness regardless of modification times, e.g. after copying the mirror
+    File::options()
+        .write(true)
+        .open(mirror.cache_file())
+        .unwrap()
+        .set_modified(SystemTime::now() - Duration::from_secs(3600))
+        .unwrap();
+    Index::load_from_cache_file(mirror.path()).unwrap();
+
+    // Packed refs
+    mirror.git(&["pack-refs", "--all"]);
+    Index::load_from_cache_file(mirror.path()).unwrap();
+
+    // A detached HEAD
+    mirror.git(&["checkout", "--quiet", "--detach", &first]);
+    assert!(matches!(
+        Index::load_from_cache_file(mirror.path()),
+        Err(Error::CacheFileStale(_))
+    ));
+    assert_eq!(
+        Index::load(mirror.path()).unwrap().head(),
+        Some(first.as_str())
+    );
+}
+
+#[test]
+fn stale_cache_file_keeps_symbols() {
+    let mirror = mini_mirror();
+    Index::load_from_mirror_directory_with_symbols(mirror.path()).unwrap();
+    File::options()
+        .write(true)
+        .open(mirror.cache_file())
+        .unwrap()
+        .set_modified(SystemTime::now() - Duration::from_secs(3600))
+        .unwrap();
+
+    // Not a git index, so all crates are rebuilt
+    assert!(Index::load(mirror.path()).unwrap().has_symbols());
+}
+
+#[test]
+fn unreadable_cache_file_keeps_symbols() {
+    let mirror = mini_mirror();
+    Index::load_from_mirror_directory_with_symbols(mirror.path()).unwrap();
+    write(mirror.cache_file(), "not json").unwrap();
+    let index = Index::rebuild_crates(mirror.path(), mirror.path(), &[]).unwrap();
+    assert!(index.has_symbols());
+
+    write(mirror.cache_file(), "not json").unwrap();
+    assert!(Index::rebuild_changed(mirror.path(), mirror.path())
+        .unwrap()
+        .has_symbols());
+
+    // A rebuild without public items forgets them
+    Index::load_from_mirror_directory(mirror.path()).unwrap();
+    write(mirror.cache_file(), "not json").unwrap();
+    assert!(!Index::load(mirror.path()).unwrap().has_symbols());
+}
+
+#[test]
+fn rebuild_changed_from_git_history() {
+    let mirror = mini_mirror();
+    let first = mirror.git_commit();
+    assert_eq!(
+        Index::load(mirror.path()).unwrap().head(),
+        Some(first.as_str())
+    );
+
+    mirror.add_crate("new", "0.1.0", Some("New crate"));
+    mirror.add_crate("abcd", "0.3.0", Some("Changed description"));
+    std::fs::remove_file(mirror.index_file("missing")).unwrap();
+    let second = mirror.git_commit();
+    // Not committed, so not re-read
+    mirror.add_version("a", "0.2.0", false);
+
+    let index = Index::load(mirror.path()).unwrap();
+    assert_eq!(index.head(), Some(second.as_str()));
+    assert_eq!(
+        index
+            .diff(&Index::load_snapshot(mirror.path()).unwrap())
+            .summary(),
+        "1 new crate, 1 new version, 1 removed crate, 1 changed description"
+    );
+    assert_eq!(index.get("a").unwrap().versions, 1);
+}
+
+#[test]
+fn rebuild_reports_progress() {
+    let mirror = mini_mirror();
+    let events = Mutex::new(vec![]);
+    Index::rebuild_with_progress(mirror.path(), mirror.path(), false, &|x| {
+        events.lock().unwrap().push(x)
+    })
+    .unwrap();
+    let events = events.into_inner().unwrap();
+
+    assert_eq!(
+        events[0],
+        Progress {
+            phase: Phase::IndexFiles,
+            done: 0,
+            total: None,
+        }
+    );
+    assert_eq!(
+        events[11],
+        Progress {
+            phase: Phase::IndexFiles,
+            done: 11,
+            total: None,
+        }
+    );
+    assert_eq!(
+        events
+            .iter()
+            .filter(|x| x.is_finished())
+            .map(|x| (x.phase, x.done))
+            .collect::<Vec<_>>(),
+        [
+            (Phase::IndexFiles, 11),
+            (Phase::Parse, 11),
+            (Phase::CrateFiles, 11),
+            (Phase::Save, 1),
+        ]
+    );
+}
+
+#[test]
+fn missing_directories() {
+    let mirror = Mirror::new();
+    let missing = mirror.path().join("missing");
+    assert!(matches!(
+        Index::load(&missing),
+        Err(Error::DirectoryNotFound(_))
+    ));
+    assert!(matches!(
+        Index::load(&mirror.config_file()),
+        Err(Error::NotADirectory(_))
+    ));
+}
+
+#[test]
+fn separate_cache_directory() {
+    let mirror = mini_mirror();
+    let cache = mirror.path().join("cache");
+    Index::load_with_cache_directory(mirror.path(), &cache).unwrap();
+
+    assert!(cache.join("search.json").is_file());
+    assert!(!mirror.cache_file().exists());
+}
+
+#[test]
+fn diff_between_rebuilds() {
+    let mirror = mini_mirror();
+    let previous = Index::load_from_mirror_directory(mirror.path()).unwrap();
+    mirror.add_crate("new", "0.1.0", Some("New crate"));
+    mirror.add_crate("abcd", "0.3.0", Some("Four letters"));
+    let index = Index::load_from_mirror_directory(mirror.path()).unwrap();
+
+    let diff = index.diff(&previous);
+    assert_eq!(diff.new_crates.len(), 1);
+    assert_eq!(diff.new_crates[0].name, "new");
+    assert_eq!(diff.new_versions.len(), 1);
+    assert_eq!(diff.new_versions[0].name, "abcd");
+}
+
+#[test]
+fn rebuild_crates_matches_full_rebuild() {
+    let mirror = mini_mirror();
+    Index::load(mirror.path()).unwrap();
+    mirror.add_crate("new", "0.1.0", Some("New crate"));
+    mirror.add_crate("abcd", "0.3.0", Some("Changed description"));
+    std::fs::remove_file(mirror.index_file("missing")).unwrap();
+
+    let names = ["new", "abcd", "missing"].map(String::from);
+    let index = Index::rebuild_crates(mirror.path(), mirror.path(), &names).unwrap();
+    assert_eq!(
+        index
+            .diff(&Index::load_snapshot(mirror.path()).unwrap())
+            .summary(),
+        "1 new crate, 1 new version, 1 removed crate, 1 changed description"
+    );
+    assert_eq!(
+        description(&index, "abcd").as_deref(),
+        Some("Changed description")
+    );
+
+    let full = Index::load_from_mirror_directory(mirror.path()).unwrap();
+    assert_eq!(
+        index.names().collect::<Vec<_>>(),
+        full.names().collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn stats() {
+    let mirror = mini_mirror();
+    Index::load(mirror.path()).unwrap();
+
+    // Computed from the cache file without reading the crate files
+    std::fs::remove_dir_all(mirror.path().join("crates")).unwrap();
+    let stats = Index::load(mirror.path()).unwrap().stats(10);
+
+    assert_eq!(stats.crates, 11);
+    assert_eq!(stats.versions, 13);
+    assert_eq!(stats.yanked_versions, 2);
+    assert_eq!(stats.yanked_only, 1);
+    assert_eq!(stats.missing_descriptions, 5);
+
+    // Every crate with a readable `Cargo.toml`, i.e. not `corrupt`, `empty`, `malformed`, or
+    // `missing`
+    assert_eq!(stats.manifests, 7);
+    let tally = |value: Option<&str>, count| Tally {
+        value: value.map(String::from),
+        count,
+    };
+    assert_eq!(stats.licenses, [tally(None, 6), tally(Some("MIT"), 1)]);
+    assert_eq!(stats.editions, [tally(Some("2015"), 7)]);
+    assert_eq!(stats.rust_versions, [tally(None, 7)]);
+
+    // Every version but the yanked ones without crate files
+    assert_eq!(stats.crate_files, 11);
+    assert!(stats.crate_file_size > 0);
+}
+
+#[test]
+fn tarball_source_matches_directory() {
+    let mirror = mini_mirror();
+    let tarball = NamedTempFile::new().unwrap();
+    let mut builder = tar::Builder::new(tarball.as_file());
+    builder.append_dir_all("mirror", mirror.path()).unwrap();
+    builder.finish().unwrap();
+
+    let directory = Index::load_from_mirror_directory(mirror.path()).unwrap();
+    let index = Index::from_source(
+        Arc::new(TarballSource::open(tarball.path()).unwrap()),
+        false,
+    )
+    .unwrap();
+
+    assert_eq!(
+        index.names().collect::<Vec<_>>(),
+        directory.names().collect::<Vec<_>>()
+    );
+    for crate_ in &directory {
+        assert_eq!(
+            description(&index, crate_.name()).as_deref(),
+            crate_.description()
+        );
+    }
+}
+
+#[test]
+fn compressed_tarball_source() {
+    let mirror = mini_mirror();
+    let tarball = NamedTempFile::new().unwrap();
+    let mut builder = tar::Builder::new(GzEncoder::new(tarball.as_file(), Compression::default()));
+    builder.append_dir_all("mirror", mirror.path()).unwrap();
+    builder.into_inner().unwrap().finish().unwrap();
+
+    let index = Index::from_source(
+        Arc::new(TarballSource::open(tarball.path()).unwrap()),
+        false,
+    )
+    .unwrap();
+    assert_eq!(description(&index, "abcd").as_deref(), Some("Four letters"));
+
+    // Crate files that don't fit in memory
+    assert!(matches!(
+        TarballSource::open_with_memory_limit(tarball.path(), 100),
+        Err(Error::TarballTooLarge { limit: 100, .. })
+    ));
+}
+
+#[test]
+fn memory_source() {
+    let mut source = MemorySource::default();
+    source.add_index_file(
+        "Foo",
+        r#"{"name":"Foo","vers":"0.1.0","deps":[],"features":{},"yanked":false}"#,
+    );
+    let index = Index::from_source(Arc::new(source), false).unwrap();
+
+    assert_eq!(index.names().collect::<Vec<_>>(), ["Foo"]);
+    let details = index.get("Foo").unwrap().details().unwrap();
+    assert_eq!(details.versions, [(Version::new(0, 1, 0), false)]);
+}
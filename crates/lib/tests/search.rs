@@ -0,0 +1,198 @@
+mod fixture;
+
+use {
+    fixture::mini_mirror,
+    panamax_search_lib::{Crate, Error, Fields, Index, Search, SearchOptions, Sort},
+};
+
+fn names(crates: &[Crate]) -> Vec<&str> {
+    crates.iter().map(|x| x.name()).collect()
+}
+
+fn queries(queries: &[&str]) -> Vec<String> {
+    queries.iter().map(|x| x.to_string()).collect()
+}
+
+fn crates(index: &Index, q: &[&str], case_insensitive: bool) -> Search {
+    index.search(&queries(q), case_insensitive).unwrap()
+}
+
+#[test]
+fn search_categories() {
+    let mirror = mini_mirror();
+    let index = Index::load(mirror.path()).unwrap();
+
+    let search = crates(&index, &["ab"], false);
+    assert_eq!(names(search.name_exact()), ["ab"]);
+    assert_eq!(names(search.name_contains()), ["abc", "abcd"]);
+    assert!(search.desc_contains().is_empty());
+
+    let search = crates(&index, &["letter"], false);
+    assert!(search.name_exact().is_empty());
+    assert!(search.name_contains().is_empty());
+    assert_eq!(names(search.desc_contains()), ["a", "abcd"]);
+}
+
+#[test]
+fn name_matches_are_not_repeated_as_description_matches() {
+    let mirror = mini_mirror();
+    let index = Index::load(mirror.path()).unwrap();
+
+    let search = crates(&index, &["yanked"], false);
+    assert_eq!(names(search.name_exact()), ["yanked"]);
+    assert_eq!(names(search.name_contains()), ["all-yanked"]);
+    assert!(search.desc_contains().is_empty());
+}
+
+#[test]
+fn case_sensitivity() {
+    let mirror = mini_mirror();
+    let index = Index::load(mirror.path()).unwrap();
+
+    assert!(crates(&index, &["LETTER"], false).is_empty());
+    assert_eq!(crates(&index, &["LETTER"], true).len(), 2);
+}
+
+#[test]
+fn multiple_queries_and_truncate() {
+    let mirror = mini_mirror();
+    let index = Index::load(mirror.path()).unwrap();
+
+    let mut search = crates(&index, &["abc", "letter"], false);
+    assert_eq!(
+        search.iter().map(|x| x.name()).collect::<Vec<_>>(),
+        ["abc", "abcd", "a"]
+    );
+
+    search.truncate(2);
+    assert_eq!(names(&search.to_vec()), ["abc", "abcd"]);
+}
+
+#[test]
+fn cargo_search_format() {
+    let mirror = mini_mirror();
+    let index = Index::load(mirror.path()).unwrap();
+
+    assert_eq!(
+        crates(&index, &["^abc"], false).to_string(false, None),
+        "abc = \"0.1.0\"     # Project section\nabcd = \"0.2.0\"    # Four letters\n"
+    );
+    assert_eq!(
+        crates(&index, &["yanked"], false).to_string(true, None),
+        "yanked = \"1.1.0\"        # Latest version yanked\n\
+         all-yanked = \"0.1.0\"    # All versions yanked\n"
+    );
+}
+
+#[test]
+fn search_builder_options() {
+    let mirror = mini_mirror();
+    let index = Index::load(mirror.path()).unwrap();
+    let names = |results: &panamax_search_lib::SearchResults| {
+        results
+            .iter()
+            .map(|x| x.name().to_string())
+            .collect::<Vec<_>>()
+    };
+
+    // Regular expressions vs literal strings
+    let q = queries(&["a.c"]);
+    let results = index.search_builder(&q).build().unwrap();
+    assert_eq!(names(&results), ["abc", "abcd"]);
+    let results = index.search_builder(&q).literal(true).build().unwrap();
+    assert!(results.iter().next().is_none());
+
+    // Fields
+    let q = queries(&["yanked"]);
+    let results = index
+        .search_builder(&q)
+        .fields(Fields::Description)
+        .build()
+        .unwrap();
+    assert_eq!(names(&results), ["all-yanked", "yanked"]);
+
+    // Sort, limit, and filters
+    let q = queries(&["a"]);
+    let results = index
+        .search_builder(&q)
+        .sort(Sort::Name)
+        .filter(|x| x.description().is_some())
+        .limit(3)
+        .build()
+        .unwrap();
+    assert_eq!(names(&results), ["a", "ab", "abc"]);
+
+    let results = index
+        .search_with(
+            &q,
+            SearchOptions {
+                fields: Fields::Name,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    assert_eq!(results.count(), 7);
+    assert_eq!(names(&results)[0], "a");
+}
+
+#[test]
+fn invalid_query() {
+    let mirror = mini_mirror();
+    let index = Index::load(mirror.path()).unwrap();
+
+    assert!(matches!(
+        index.search_builder(&queries(&["("])).build(),
+        Err(Error::InvalidQuery(_))
+    ));
+    assert!(matches!(
+        index.search(&queries(&["("]), false),
+        Err(Error::InvalidQuery(_))
+    ));
+}
+
+#[test]
+fn prefix_and_filter() {
+    let mirror = mini_mirror();
+    let index = Index::load(mirror.path()).unwrap();
+
+    assert_eq!(
+        index.prefix("ab").map(|x| x.name()).collect::<Vec<_>>(),
+        ["ab", "abc", "abcd"]
+    );
+
+    let described = index.filter(|x| x.description().is_some());
+    assert_eq!(described.len(), 6);
+    assert!(!described.contains("missing"));
+}
+
+#[test]
+fn search_symbols_includes_conflicts() {
+    let first = mini_mirror();
+    let second = mini_mirror();
+    let index = Index::merge(vec![
+        (
+            String::from("first"),
+            Index::load_from_mirror_directory_with_symbols(first.path()).unwrap(),
+        ),
+        (
+            String::from("second"),
+            Index::load_from_mirror_directory_with_symbols(second.path()).unwrap(),
+        ),
+    ]);
+
+    let search = index.search_symbols(&queries(&["a"]), false).unwrap();
+    let results = search
+        .name_exact
+        .iter()
+        .chain(&search.name_contains)
+        .map(|(crate_, symbol)| {
+            format!("{} {} {symbol}", crate_.registry().unwrap(), crate_.name())
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(results, ["first a fn a::a", "second a fn a::a"]);
+
+    assert!(matches!(
+        index.search_symbols(&queries(&["("]), false),
+        Err(Error::InvalidQuery(_))
+    ));
+}